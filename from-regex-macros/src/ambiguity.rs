@@ -0,0 +1,124 @@
+use std::collections::{HashMap, VecDeque};
+
+use regex_automata::dfa::{dense, Automaton, StartKind};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+
+/// Upper bound on the heap used to build each pattern's DFA. Patterns that
+/// need more than this aren't checked.
+const DFA_SIZE_LIMIT: usize = 10 * (1 << 20);
+
+/// Upper bound on the number of state pairs to explore when searching for a
+/// common match. Searches that need more than this give up.
+///
+/// Every pair of variants is checked at compile time, and each state pair
+/// tries all 256 bytes, so this caps a check at around 2.5 million
+/// transitions (and the enum's at that many per pair of variants).
+const MAX_STATE_PAIRS: usize = 10_000;
+
+/// A DFA accepting exactly the strings its pattern matches in their entirety
+struct FullMatch {
+    dfa: dense::DFA<Vec<u32>>,
+    start: StateID,
+}
+
+impl FullMatch {
+    fn new(pattern: &str) -> Option<Self> {
+        // `MatchKind::All`, since leftmost-first semantics would stop at the
+        // preferred alternative, rather than one that spans the input
+        let dfa = dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored)
+                    .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+                    .determinize_size_limit(Some(DFA_SIZE_LIMIT)),
+            )
+            .build(pattern)
+            .ok()?;
+        let start = dfa
+            .start_state(&start::Config::new().anchored(Anchored::Yes))
+            .ok()?;
+        Some(Self { dfa, start })
+    }
+
+    /// Whether the input consumed to reach `state` is a full match
+    fn accepts(&self, state: StateID) -> bool {
+        self.dfa.is_match_state(self.dfa.next_eoi_state(state))
+    }
+
+    /// The state after consuming `byte`, unless no match is possible from it
+    fn next(&self, state: StateID, byte: u8) -> Option<StateID> {
+        let next = self.dfa.next_state(state, byte);
+        if self.dfa.is_dead_state(next) || self.dfa.is_quit_state(next) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+}
+
+/// Find a string that both patterns match in its entirety, if there is one
+/// (and it can be found in reasonable time). Shorter examples, made of
+/// printable ASCII where possible, are found first.
+pub fn common_match(a: &str, b: &str) -> Option<String> {
+    let a = FullMatch::new(a)?;
+    let b = FullMatch::new(b)?;
+
+    // Breadth first search through the product of both DFAs, remembering how
+    // each pair of states was reached so the example can be rebuilt
+    let start = (a.start, b.start);
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(start, None);
+    queue.push_back(start);
+
+    while let Some(pair) = queue.pop_front() {
+        if a.accepts(pair.0) && b.accepts(pair.1) {
+            let mut bytes = Vec::new();
+            let mut current = pair;
+            while let Some((parent, byte)) = parents[&current] {
+                bytes.push(byte);
+                current = parent;
+            }
+            bytes.reverse();
+            return Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+
+        let printable = b' '..=b'~';
+        let others = (0..b' ').chain(0x7f..=0xff);
+        for byte in printable.chain(others) {
+            let next = match (a.next(pair.0, byte), b.next(pair.1, byte)) {
+                (Some(next_a), Some(next_b)) => (next_a, next_b),
+                _ => continue,
+            };
+            if !parents.contains_key(&next) {
+                if parents.len() >= MAX_STATE_PAIRS {
+                    return None;
+                }
+                parents.insert(next, Some((pair, byte)));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::common_match;
+
+    #[test]
+    fn common_matches() {
+        assert_eq!(common_match("[a-z]+", "if|else").as_deref(), Some("if"));
+        assert_eq!(
+            common_match("c", "(?P<a>a)?(?P<b>b)?c").as_deref(),
+            Some("c")
+        );
+        assert_eq!(common_match("a|ab", "ab").as_deref(), Some("ab"));
+        assert_eq!(common_match("a", "ab"), None);
+        assert_eq!(common_match("[0-9]+", "[a-z]+"), None);
+    }
+}
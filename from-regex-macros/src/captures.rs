@@ -1,29 +1,39 @@
 use std::collections::HashMap;
 
-use lazy_static::lazy_static;
+use from_regex_syntax::participation;
 use quote::quote;
-use regex::Regex;
 use syn::spanned::Spanned;
 
-pub type Groups<'a> = HashMap<&'a str, bool>;
+pub type Groups = HashMap<String, bool>;
 
-// TODO: Check edges. Regex::capture_names might help, here, but it doesn't tell us if they're optional
-const CAPTURE_GROUP_PATTERN: &str = r"[\(]*\?P<(?P<group>[A-z0-9_]+)>[^\)]*[\)]*(?P<optional>\?)?";
-lazy_static! {
-    static ref CAPTURE_GROUP_REGEX: Regex = Regex::new(CAPTURE_GROUP_PATTERN).unwrap();
-}
-
-pub fn from_regex_pattern(pat: &str) -> Groups {
-    let groups = CAPTURE_GROUP_REGEX
-        .captures_iter(pat)
-        .map(|cap| {
-            let name = cap.name("group").unwrap().as_str();
-            let optional = cap.name("optional").is_some();
-            (name, optional)
+/// The named groups of a pattern, and whether each is optional (might not
+/// participate in a match). Taken from the parsed pattern, so that e.g.
+/// groups mentioned in verbose mode comments aren't included. Invalid
+/// patterns have no groups (and fail to compile in the generated code).
+pub fn from_regex_pattern(pattern: &str) -> Groups {
+    let hir = match regex_syntax::parse(pattern) {
+        Ok(hir) => hir,
+        Err(_) => return Groups::new(),
+    };
+    participation(&hir)
+        .into_iter()
+        .map(|(group, within)| {
+            let optional = !within.contains(&None);
+            (group, optional)
         })
-        .collect::<HashMap<_, _>>();
+        .collect()
+}
 
-    groups
+/// The name of `group` (a field's group, e.g. `name`, or `_0` for the first
+/// of a tuple) prefixed with `prefix` (a variant's name): `{prefix}_{name}`
+/// for named fields, and `{prefix}_{i}` for tuple fields
+pub fn prefixed_group_name(prefix: &str, group: &str) -> String {
+    match group.strip_prefix('_') {
+        Some(index) if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{}_{}", prefix, index)
+        }
+        _ => format!("{}_{}", prefix, group),
+    }
 }
 
 // Use prefix to add a prefix to the capture group name (necessary for combined regex matching)
@@ -37,7 +47,7 @@ pub fn impl_fields_from_capture(
             fields.iter().map(|field| {
                     let name = field.ident.clone().unwrap();
                     let name_val = if let Some(prefix) = prefix {
-                        format!("{}_{}", prefix, name)
+                        prefixed_group_name(prefix, &name.to_string())
                     } else {
                         name.to_string()
                     };
@@ -56,7 +66,7 @@ pub fn impl_fields_from_capture(
         }) => {
             fields.iter().enumerate().map(|(i, field)| {
                     let name_val = if let Some(prefix) = prefix {
-                        format!("{}_{}", prefix, i)
+                        prefixed_group_name(prefix, &format!("_{}", i))
                     } else {
                         format!("_{}", i)
                     };
@@ -75,3 +85,151 @@ pub fn impl_fields_from_capture(
         syn::Fields::Unit => (Vec::new(), Vec::new()),
     }
 }
+
+/// Generate an expression looking up a capture group in `captures` by its
+/// name, which starts with the runtime `prefix` (see `FromCaptures`)
+pub fn impl_prefixed_group(group: &syn::LitStr) -> proc_macro2::TokenStream {
+    quote! { captures.name(&format!("{}{}", prefix, #group)) }
+}
+
+/// Generate statements reading each field from a capture group, for
+/// `FromCaptures`. Groups are named as in the derived pattern, prefixed with
+/// the variant name (as for `impl_fields_from_capture`) if given, and with
+/// the runtime `prefix`.
+///
+/// Unlike `impl_fields_from_capture` the pattern is unknown, so whether a
+/// group is optional is decided by its field: `Option` fields are `None` if
+/// their group didn't participate, and other fields need it to.
+pub fn impl_fields_from_prefixed_capture(
+    fields: &syn::Fields,
+    variant: Option<&str>,
+) -> (Vec<syn::Ident>, Vec<proc_macro2::TokenStream>) {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let (name, group) = prefixed_field_group(i, field, variant);
+            let group = impl_prefixed_group(&syn::LitStr::new(&group, field.span()));
+            let statement = if is_option(&field.ty) {
+                quote! { let #name = #group.map(|mat| mat.as_str().into()); }
+            } else {
+                quote! { let #name = #group?.as_str().into(); }
+            };
+            (name, statement)
+        })
+        .unzip()
+}
+
+/// The (unprefixed) names of the groups read by
+/// `impl_fields_from_prefixed_capture` that must participate in a match
+pub fn required_prefixed_groups(fields: &syn::Fields, variant: Option<&str>) -> Vec<String> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !is_option(&field.ty))
+        .map(|(i, field)| prefixed_field_group(i, field, variant).1)
+        .collect()
+}
+
+/// The local a field is read into, and the name of its group
+fn prefixed_field_group(
+    i: usize,
+    field: &syn::Field,
+    variant: Option<&str>,
+) -> (syn::Ident, String) {
+    let name = match &field.ident {
+        Some(ident) => ident.clone(),
+        None => syn::Ident::new(&format!("_{}", i), field.span()),
+    };
+    let group = match variant {
+        Some(variant) => prefixed_group_name(variant, &name.to_string()),
+        None => name.to_string(),
+    };
+    (name, group)
+}
+
+/// Pairs of the group a requirement is within (if any) and the groups it
+/// requires, as for `FromCaptures::group_requirements`
+pub type Requirements = Vec<(Option<String>, Vec<String>)>;
+
+/// Check that `pattern` has the groups `requirements` need (as
+/// `from_regex::Parser::new` would), describing the first problem found
+pub fn check_requirements(pattern: &str, requirements: &Requirements) -> Result<(), String> {
+    let hir = regex_syntax::parse(pattern).map_err(|err| format!("Invalid pattern: {}", err))?;
+    from_regex_syntax::check_requirements(
+        requirements
+            .iter()
+            .map(|(within, groups)| (within.as_deref(), groups.iter().map(String::as_str))),
+        &participation(&hir),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Generate `FromCaptures::group_requirements`
+pub fn impl_group_requirements(requirements: Requirements) -> proc_macro2::TokenStream {
+    let requirements = requirements.into_iter().map(|(within, groups)| {
+        let within = match within {
+            Some(within) => quote! { Some(#within) },
+            None => quote! { None },
+        };
+        quote! {
+            from_regex::GroupRequirement {
+                within: #within,
+                groups: vec![#(#groups),*],
+            }
+        }
+    });
+    quote! {
+        fn group_requirements() -> Vec<from_regex::GroupRequirement> {
+            vec![#(#requirements),*]
+        }
+    }
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath { path, .. }) => path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_regex_pattern, prefixed_group_name};
+
+    #[test]
+    fn groups() {
+        let groups = from_regex_pattern(r"(?P<a>a)(?P<b>b)?(?:(?P<c>c)|d)(?P<e>(?P<f>f)+)");
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![
+                (String::from("a"), false),
+                (String::from("b"), true),
+                (String::from("c"), true),
+                (String::from("e"), false),
+                (String::from("f"), false),
+            ]
+        );
+
+        // Groups only mentioned in comments aren't included
+        let groups = from_regex_pattern("(?x) (?P<a> a ) # not (?P<b> b )?, or (?P<a>a)?\n");
+        assert_eq!(
+            groups.into_iter().collect::<Vec<_>>(),
+            vec![(String::from("a"), false)]
+        );
+    }
+
+    #[test]
+    fn prefixed_group_names() {
+        assert_eq!(prefixed_group_name("Variant", "name"), "Variant_name");
+        assert_eq!(prefixed_group_name("Variant", "_0"), "Variant_0");
+        assert_eq!(prefixed_group_name("Variant", "_12"), "Variant_12");
+        assert_eq!(prefixed_group_name("Variant", "_name"), "Variant__name");
+        assert_eq!(prefixed_group_name("Variant", "_"), "Variant__");
+    }
+}
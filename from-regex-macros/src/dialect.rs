@@ -0,0 +1,164 @@
+use quote::quote;
+use syn::spanned::Spanned;
+
+use crate::captures::Requirements;
+
+const ATTRIBUTE_DIALECT: &str = "dialect";
+const ATTRIBUTE_PATTERN: &str = "pattern";
+const ATTRIBUTE_PATTERN_FILE: &str = "pattern_file";
+
+/// A named pattern, from `#[from_regex(pattern = "regex", dialect = "name")]`
+/// (or `pattern_file = "path"`)
+pub struct Dialect {
+    pub name: syn::LitStr,
+    pub pattern: syn::LitStr,
+    // Full path of the file the pattern was read from (if any)
+    file: Option<String>,
+}
+
+/// Separate the attributes declaring dialects from the rest (so that their
+/// patterns aren't taken for the item's own)
+pub fn split(attrs: &[syn::Attribute]) -> syn::Result<(Vec<syn::Attribute>, Vec<Dialect>)> {
+    let mut rest = Vec::new();
+    let mut dialects: Vec<Dialect> = Vec::new();
+    for attr in attrs {
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) if list.path.is_ident(crate::ATTRIBUTE) => list,
+            _ => {
+                rest.push(attr.clone());
+                continue;
+            }
+        };
+
+        let mut name = None;
+        let mut pattern = None;
+        let mut file = None;
+        for nested in list.nested.iter() {
+            if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Str(lit),
+                ..
+            })) = nested
+            {
+                if path.is_ident(ATTRIBUTE_DIALECT) {
+                    name = Some(lit.clone());
+                } else if path.is_ident(ATTRIBUTE_PATTERN) {
+                    pattern = Some(lit.clone());
+                } else if path.is_ident(ATTRIBUTE_PATTERN_FILE) {
+                    file = Some(lit.clone());
+                }
+            }
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => {
+                rest.push(attr.clone());
+                continue;
+            }
+        };
+        let (pattern, file) = match (pattern, file) {
+            (Some(pattern), None) => (pattern, None),
+            (None, Some(file)) => {
+                let (pattern, file) = crate::pattern_file::read(&file)?;
+                (pattern, Some(file))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "A dialect needs a `pattern` or `pattern_file` in the same attribute",
+                ))
+            }
+        };
+        if list.nested.len() > 2 {
+            return Err(syn::Error::new(
+                list.nested.span(),
+                "A dialect's attribute can only have a `dialect` and its pattern",
+            ));
+        }
+        if dialects
+            .iter()
+            .any(|dialect| dialect.name.value() == name.value())
+        {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("Dialect `{}` is declared twice", name.value()),
+            ));
+        }
+        if let Err(err) = regex::Regex::new(&pattern.value()) {
+            return Err(syn::Error::new(
+                pattern.span(),
+                format!("Invalid pattern for dialect `{}`: {}", name.value(), err),
+            ));
+        }
+        dialects.push(Dialect {
+            name,
+            pattern,
+            file,
+        });
+    }
+    Ok((rest, dialects))
+}
+
+/// Check that every dialect's pattern has the groups `ident` needs to be
+/// constructed from it (so that its parser can always be built)
+pub fn check(
+    ident: &syn::Ident,
+    dialects: &[Dialect],
+    requirements: &Requirements,
+) -> syn::Result<()> {
+    for dialect in dialects {
+        if let Err(err) =
+            crate::captures::check_requirements(&dialect.pattern.value(), requirements)
+        {
+            return Err(syn::Error::new(
+                dialect.pattern.span(),
+                format!(
+                    "Dialect `{}` can't construct `{}`: {}",
+                    dialect.name.value(),
+                    ident,
+                    err
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Generate `Dialects` for `ident` (if it has any dialects), compiling each
+/// dialect's parser the first time one is used. Patterns are checked with
+/// [`check`] beforehand, so building the parsers doesn't fail.
+pub fn impl_dialects(ident: &syn::Ident, dialects: &[Dialect]) -> proc_macro2::TokenStream {
+    if dialects.is_empty() {
+        return quote! {};
+    }
+
+    let names = dialects
+        .iter()
+        .map(|dialect| &dialect.name)
+        .collect::<Vec<_>>();
+    let patterns = dialects.iter().map(|dialect| &dialect.pattern);
+    let tracking = dialects
+        .iter()
+        .filter_map(|dialect| dialect.file.as_deref())
+        .map(crate::pattern_file::impl_tracking);
+    quote! {
+        #(#tracking)*
+
+        impl from_regex::Dialects for #ident {
+            fn dialects() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn dialect_parser(dialect: &str) -> Option<&'static from_regex::Parser<Self>> {
+                from_regex::lazy_static! {
+                    static ref PARSERS: Vec<from_regex::Parser<#ident>> = vec![#(
+                        from_regex::Parser::new(#patterns).expect("Failed to compile regex")
+                    ),*];
+                }
+                let index = Self::dialects().iter().position(|name| *name == dialect)?;
+                Some(&PARSERS[index])
+            }
+        }
+    }
+}
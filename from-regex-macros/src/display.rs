@@ -0,0 +1,54 @@
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// A pattern binding every field of a struct or variant to a local (the
+/// field's name, or `_0`, `_1`, ... for tuple fields), and those locals
+pub fn bind_fields(fields: &syn::Fields) -> (proc_macro2::TokenStream, Vec<syn::Ident>) {
+    let names = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => syn::Ident::new(&format!("_{}", i), field.span()),
+        })
+        .collect::<Vec<_>>();
+
+    let pattern = match fields {
+        syn::Fields::Named(_) => quote! { { #(#names),* } },
+        syn::Fields::Unnamed(_) => quote! { ( #(#names),* ) },
+        syn::Fields::Unit => quote! {},
+    };
+    (pattern, names)
+}
+
+/// Generate a statement writing a value with its `display` format (where
+/// fields are referred to by the names from [`bind_fields`]), or else by
+/// displaying its `inner` field
+pub fn impl_write(
+    display: Option<&syn::LitStr>,
+    inner: Option<&syn::Ident>,
+) -> Option<proc_macro2::TokenStream> {
+    match (display, inner) {
+        (Some(display), _) => Some(quote! { write!(f, #display) }),
+        (None, Some(inner)) => Some(quote! { std::fmt::Display::fmt(#inner, f) }),
+        (None, None) => None,
+    }
+}
+
+/// Generate the `Display` implementation for `ident`, matching `self`
+/// against the given arms
+pub fn impl_display(
+    ident: &syn::Ident,
+    arms: Vec<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    quote! {
+        impl std::fmt::Display for #ident {
+            #[allow(unused_variables)]
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #( #arms, )*
+                }
+            }
+        }
+    }
+}
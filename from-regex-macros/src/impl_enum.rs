@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
 
 use heck::{ShoutySnekCase, SnekCase};
 use proc_macro_error::abort;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
 
-use crate::captures;
+use crate::transparent::Transparent;
+use crate::{ambiguity, captures};
 
 // TODO: make sure variants match full text
 
@@ -13,34 +14,70 @@ pub struct Item<'a> {
     ident: &'a syn::Ident,
     attrs: ItemAttributes,
     variants: Vec<Variant<'a>>,
+    dialects: Vec<crate::dialect::Dialect>,
 }
 
 pub struct ItemAttributes {
     match_mode: MatchMode,
+    ambiguity: Ambiguity,
+    lexer: bool,
+    max_len: Option<syn::LitInt>,
+    // Only recorded to be rejected, since enums parse with their variants'
+    // patterns
+    default_dialect: Option<syn::LitStr>,
 }
 // TODO: document match mode... First generates multiple regex consts,
-// longest only generates a master regex for the whole enum
+// longest also generates a master regex for the whole enum (for `from_regex`)
+#[derive(Default)]
 enum MatchMode {
     First,
+    // Search for every variant and keep the leftmost-longest matches
+    #[default]
     Longest,
 }
 
-// Regex default or (a)|(b) is to match the longest variant
-impl Default for MatchMode {
-    fn default() -> Self {
-        Self::Longest
-    }
+// What to do when two patterned variants (with the same priority) can match
+// the same input. `First` match mode resolves these by declaration order, so
+// only `Longest` checks by default
+enum Ambiguity {
+    Allow,
+    Warn,
+    Deny,
 }
 
 const ENUM_ATTRIBUTE_MATCH_MODE: &str = "match_mode";
 const ENUM_ATTRIBUTE_MATCH_MODE_LONGEST: &str = "longest";
 const ENUM_ATTRIBUTE_MATCH_MODE_FIRST: &str = "first";
+const ENUM_ATTRIBUTE_AMBIGUITY: &str = "ambiguity";
+const ENUM_ATTRIBUTE_AMBIGUITY_ALLOW: &str = "allow";
+const ENUM_ATTRIBUTE_AMBIGUITY_WARN: &str = "warn";
+const ENUM_ATTRIBUTE_AMBIGUITY_DENY: &str = "deny";
+const ENUM_ATTRIBUTE_LEXER: &str = "lexer";
+const ENUM_ATTRIBUTE_MAX_LEN: &str = "max_len";
+const ENUM_ATTRIBUTE_DEFAULT_DIALECT: &str = "default_dialect";
 
 impl From<&[syn::Attribute]> for ItemAttributes {
     fn from(attrs: &[syn::Attribute]) -> Self {
         let mut match_mode = MatchMode::Longest;
+        let mut ambiguity = None;
+        let mut lexer = false;
+        let mut max_len = None;
+        let mut default_dialect = None;
         for meta in crate::Attributes::from(attrs) {
-            if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+            if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = &meta {
+                if path.is_ident(ENUM_ATTRIBUTE_LEXER) {
+                    lexer = true;
+                }
+            } else if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Int(lit),
+                ..
+            })) = &meta
+            {
+                if path.is_ident(ENUM_ATTRIBUTE_MAX_LEN) {
+                    max_len = Some(lit.clone());
+                }
+            } else if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
                 path,
                 lit: syn::Lit::Str(lit),
                 ..
@@ -52,11 +89,31 @@ impl From<&[syn::Attribute]> for ItemAttributes {
                         ENUM_ATTRIBUTE_MATCH_MODE_FIRST => match_mode = MatchMode::First,
                         other => abort!(lit.span(), "Unknown match mode: {}", other),
                     }
+                } else if path.is_ident(ENUM_ATTRIBUTE_AMBIGUITY) {
+                    match lit.value().as_str() {
+                        ENUM_ATTRIBUTE_AMBIGUITY_ALLOW => ambiguity = Some(Ambiguity::Allow),
+                        ENUM_ATTRIBUTE_AMBIGUITY_WARN => ambiguity = Some(Ambiguity::Warn),
+                        ENUM_ATTRIBUTE_AMBIGUITY_DENY => ambiguity = Some(Ambiguity::Deny),
+                        other => abort!(lit.span(), "Unknown ambiguity level: {}", other),
+                    }
+                } else if path.is_ident(ENUM_ATTRIBUTE_DEFAULT_DIALECT) {
+                    default_dialect = Some(lit);
                 }
             }
         }
 
-        Self { match_mode }
+        let ambiguity = ambiguity.unwrap_or(match match_mode {
+            MatchMode::Longest => Ambiguity::Warn,
+            MatchMode::First => Ambiguity::Allow,
+        });
+
+        Self {
+            match_mode,
+            ambiguity,
+            lexer,
+            max_len,
+            default_dialect,
+        }
     }
 }
 
@@ -65,7 +122,16 @@ impl<'a> quote::ToTokens for Item<'a> {
         tokens.extend(match self.attrs.match_mode {
             MatchMode::Longest => self.to_tokens_longest(),
             MatchMode::First => self.to_tokens_first(),
-        })
+        });
+        tokens.extend(self.check_ambiguity());
+        tokens.extend(self.impl_display());
+        tokens.extend(self.impl_from_captures());
+        tokens.extend(crate::dialect::impl_dialects(self.ident, &self.dialects));
+        for variant in self.variants.iter() {
+            if let Some(path) = &variant.attrs.pattern_file {
+                tokens.extend(crate::pattern_file::impl_tracking(path));
+            }
+        }
     }
 }
 
@@ -75,59 +141,309 @@ impl<'a> Item<'a> {
         attrs: &'a [syn::Attribute],
         variants: V,
     ) -> Self {
-        Self {
-            ident,
-            attrs: attrs.into(),
-            variants: variants.map(Variant::new).collect(),
+        Self::try_new(ident, attrs, variants).unwrap_or_else(|err| abort!(err.span(), "{}", err))
+    }
+
+    /// [`Item::new`], returning errors with its dialects (rather than
+    /// aborting)
+    fn try_new<V: Iterator<Item = &'a syn::Variant>>(
+        ident: &'a syn::Ident,
+        attrs: &'a [syn::Attribute],
+        variants: V,
+    ) -> syn::Result<Self> {
+        // Generated code tries (and searches for) variants in this order, so
+        // sort by priority (highest first). Sorting is stable, so variants
+        // with the same priority stay in declaration order
+        let mut variants = variants.map(Variant::new).collect::<Vec<_>>();
+        variants.sort_by_key(|variant| Reverse(variant.attrs.priority));
+
+        for transparent in variants
+            .iter()
+            .filter_map(|variant| variant.transparent.as_ref())
+        {
+            transparent.check_recursion(ident);
         }
+
+        let (attrs, dialects) = crate::dialect::split(attrs)?;
+        let attrs = ItemAttributes::from(attrs.as_slice());
+        if let Some(default) = &attrs.default_dialect {
+            return Err(syn::Error::new(
+                default.span(),
+                "Enums can't have a default dialect, since they parse with their variants' patterns",
+            ));
+        }
+        if !attrs.lexer {
+            if let Some(variant) = variants.iter().find(|variant| variant.attrs.lex.is_some()) {
+                abort!(
+                    variant.ident.span(),
+                    "`skip`, `state`, `push` and `pop` can only be used with `#[from_regex(lexer)]`"
+                );
+            }
+        }
+
+        let item = Self {
+            ident,
+            attrs,
+            variants,
+            dialects,
+        };
+        crate::dialect::check(ident, &item.dialects, &item.group_requirements())?;
+        Ok(item)
+    }
+
+    /// The groups `FromCaptures` needs within each variant's group
+    fn group_requirements(&self) -> crate::captures::Requirements {
+        self.variants
+            .iter()
+            .filter(|variant| !matches!(variant.attrs.pattern, VariantPattern::None))
+            .map(|variant| {
+                let name = variant.ident.to_string();
+                let groups = match variant.transparent {
+                    Some(_) => Vec::new(),
+                    None => crate::captures::required_prefixed_groups(variant.fields, Some(&name)),
+                };
+                (Some(name), groups)
+            })
+            .collect()
     }
 
     fn name_shouty(&self) -> String {
         self.ident.to_string().TO_SHOUTY_SNEK_CASE()
     }
 
-    /// Get the constructor for the default variant (if it exists)
-    fn default_constructor(&self) -> Option<proc_macro2::TokenStream> {
-        let mut default = None;
+    /// Identifier for the regex constant matching only the given variant
+    fn variant_regex_ident(&self, variant: &Variant) -> syn::Ident {
+        syn::Ident::new(
+            &format!(
+                "{}_{}_REGEX",
+                self.name_shouty(),
+                variant.ident.to_string().TO_SHOUTY_SNEK_CASE()
+            ),
+            self.ident.span(),
+        )
+    }
+
+    /// Identifier for the constant with the patterns of the variants active
+    /// in the given (numbered) lexer state
+    fn lex_patterns_ident(&self, state: usize) -> syn::Ident {
+        syn::Ident::new(
+            &format!("{}_LEX_PATTERNS_{}", self.name_shouty(), state),
+            self.ident.span(),
+        )
+    }
+
+    /// Identifier for the regex constant matching only the given variant,
+    /// anchored to the entire input
+    fn variant_full_regex_ident(&self, variant: &Variant) -> syn::Ident {
+        syn::Ident::new(
+            &format!(
+                "{}_{}_FULL_REGEX",
+                self.name_shouty(),
+                variant.ident.to_string().TO_SHOUTY_SNEK_CASE()
+            ),
+            self.ident.span(),
+        )
+    }
+
+    /// Check each pair of patterned variants with the same priority for an
+    /// input they both match, and warn about (or abort on) any found.
+    ///
+    /// Warnings can't be emitted from proc macros on stable, so they are
+    /// generated as the use of a deprecated item, with the warning as the
+    /// deprecation note.
+    fn check_ambiguity(&self) -> proc_macro2::TokenStream {
+        let mut warnings = proc_macro2::TokenStream::new();
+        if matches!(self.attrs.ambiguity, Ambiguity::Allow) {
+            return warnings;
+        }
+
+        let patterned = self
+            .variants
+            .iter()
+            .filter_map(|variant| match &variant.attrs.pattern {
+                VariantPattern::Some(pattern) => Some((variant, pattern.value())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (first, first_pattern)) in patterned.iter().enumerate() {
+            for (second, second_pattern) in patterned[i + 1..].iter() {
+                // Lexers never try variants from different states at once
+                if first.attrs.priority != second.attrs.priority
+                    || first.attrs.lex_state() != second.attrs.lex_state()
+                {
+                    continue;
+                }
+
+                if let Some(example) = ambiguity::common_match(first_pattern, second_pattern) {
+                    let message = format!(
+                        "Variants `{}` and `{}` of `{}` can both match {:?} (`{}` is chosen, by declaration order). Set a `priority` on one of them, or `ambiguity = \"allow\"` on the enum",
+                        first.ident, second.ident, self.ident, example, first.ident
+                    );
+                    match self.attrs.ambiguity {
+                        Ambiguity::Deny => abort!(second.ident.span(), "{}", message),
+                        _ => warnings.extend(quote_spanned! { second.ident.span() =>
+                            const _: () = {
+                                #[deprecated(note = #message)]
+                                struct AmbiguousVariants;
+                                let _ = AmbiguousVariants;
+                            };
+                        }),
+                    }
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Generate a `Display` implementation, if any variant has a `display`
+    /// format. Transparent variants without one display their inner value,
+    /// and every other variant needs one.
+    fn impl_display(&self) -> Option<proc_macro2::TokenStream> {
+        self.variants
+            .iter()
+            .find(|variant| variant.attrs.display.is_some())?;
+
+        let arms = self
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = variant.ident;
+                let (pattern, names) = crate::display::bind_fields(variant.fields);
+                let inner = variant
+                    .transparent
+                    .as_ref()
+                    .map(|transparent| &names[transparent.inner_index()]);
+                let write = crate::display::impl_write(variant.attrs.display.as_ref(), inner)
+                    .unwrap_or_else(|| {
+                        abort!(
+                            ident.span(),
+                            "Variant `{}` needs a `display` format, since others have one",
+                            ident
+                        )
+                    });
+                quote! { Self::#ident #pattern => #write }
+            })
+            .collect();
+        Some(crate::display::impl_display(self.ident, arms))
+    }
+
+    /// Generate `FromCaptures`, trying each variant (in order) whose group
+    /// (named after it, as for `match_mode = "longest"`) participated
+    fn impl_from_captures(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        let mut from_capture_impls = Vec::new();
+        let mut from_captures_impls = Vec::new();
         for variant in self.variants.iter() {
-            if variant.attrs.default {
-                if let Some((existing, _)) = default {
-                    abort!(variant.ident.span(), "More than one default varaiant. {} identified as default, but {} is already set", variant.ident, existing);
-                }
-
-                let ident = self.ident;
-                let variant_ident = variant.ident;
-
-                default = Some((
-                    variant_ident,
-                    match &variant.fields {
-                        syn::Fields::Named(syn::FieldsNamed { named, .. }) => {
-                            let fields = named
-                                .iter()
-                                .map(|syn::Field { ident, ty, .. }| {
-                                    quote! { #ident: <#ty>::default() }
-                                })
-                                .collect::<Vec<_>>();
-                            quote! { Some(#ident::#variant_ident { #( #fields, )* }) }
-                        }
+            if matches!(variant.attrs.pattern, VariantPattern::None) {
+                continue;
+            }
+            let fn_ident = syn::Ident::new(
+                &format!(
+                    "__from_regex_prefixed_capture_{}",
+                    variant.ident.to_string().to_snek_case()
+                ),
+                variant.ident.span(),
+            );
+            let group = crate::captures::impl_prefixed_group(&syn::LitStr::new(
+                &variant.ident.to_string(),
+                variant.ident.span(),
+            ));
+            let body = match variant.transparent_inner_type() {
+                Some(inner) => {
+                    let constructor = variant.impl_transparent_constructor(quote! { inner });
+                    quote! {
+                        let inner = <#inner>::from_regex(#group?.as_str())?;
+                        Some(#constructor)
+                    }
+                }
+                None => {
+                    let variant_ident = variant.ident;
+                    let (names, statements) = crate::captures::impl_fields_from_prefixed_capture(
+                        variant.fields,
+                        Some(&variant_ident.to_string()),
+                    );
+                    let constructor = match variant.fields {
+                        syn::Fields::Named(_) => quote! { Self::#variant_ident { #(#names),* } },
+                        syn::Fields::Unnamed(_) => quote! { Self::#variant_ident( #(#names),* ) },
+                        syn::Fields::Unit => quote! { Self::#variant_ident },
+                    };
+                    quote! {
+                        #group?;
+                        #(#statements)*
+                        Some(#constructor)
+                    }
+                }
+            };
+            from_capture_impls.push(quote! {
+                fn #fn_ident(captures: &from_regex::Captures, prefix: &str) -> Option<Self> {
+                    #body
+                }
+            });
+            from_captures_impls.push(quote! {
+                if let Some(value) = Self::#fn_ident(captures, prefix) {
+                    return Some(value);
+                }
+            });
+        }
+
+        let impl_group_requirements =
+            crate::captures::impl_group_requirements(self.group_requirements());
+        quote! {
+            impl #ident {
+                #(
+                    #from_capture_impls
+                )*
+            }
+            impl from_regex::FromCaptures for #ident {
+                #[allow(unused_variables)]
+                fn from_captures_prefixed(captures: &from_regex::Captures, prefix: &str) -> Option<Self> {
+                    #(
+                        #from_captures_impls
+                    )*
+                    None
+                }
 
-                        syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed, .. }) => {
-                            let fields = unnamed
-                                .iter()
-                                .map(|syn::Field { ty, .. }| {
-                                    quote! { <#ty>::default() }
-                                })
-                                .collect::<Vec<_>>();
+                #impl_group_requirements
+            }
+        }
+    }
 
-                            quote! { Some(#ident::#variant_ident ( #( #fields, )* )) }
-                        }
+    /// Generate `variant_name`, naming every variant (not only those that
+    /// can be matched)
+    fn impl_variant_name(&self) -> proc_macro2::TokenStream {
+        let arms = self.variants.iter().map(|variant| {
+            let ident = variant.ident;
+            let name = ident.to_string();
+            quote! { Self::#ident { .. } => #name }
+        });
+        quote! {
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #( #arms, )*
+                }
+            }
+        }
+    }
 
-                        syn::Fields::Unit => quote! { Some(#ident::#variant_ident) },
-                    },
-                ))
+    /// Get the constructor for the default variant (if it exists), given
+    /// the unmatched input `s`
+    fn default_constructor(&self) -> Option<proc_macro2::TokenStream> {
+        let mut default = None;
+        for variant in self.variants.iter() {
+            if variant.attrs.default.is_some() {
+                if let Some(existing) = default {
+                    let existing: &Variant = existing;
+                    abort!(variant.ident.span(), "More than one default variant. {} identified as default, but {} is already set", variant.ident, existing.ident);
+                }
+                default = Some(variant);
             }
         }
-        default.map(|(_, stream)| stream)
+        default.map(|variant| {
+            let constructor = variant.impl_default_constructor();
+            quote! { Some(#constructor) }
+        })
     }
 
     /// [`ToTokens`] for [`MatchMode::Longest`] with combined regex
@@ -140,7 +456,7 @@ impl<'a> Item<'a> {
     /// captures) and the token stream defining the regex constant.
     ///
     /// Capture groups in each variant will be prepended with the variant and
-    /// wrapped with a varaiant-specific capture group.
+    /// wrapped with a variant-specific capture group.
     ///
     /// For example:
     /// ```ignore
@@ -159,20 +475,38 @@ impl<'a> Item<'a> {
     /// Generates
     /// ```ignore
     /// lazy_static! {
-    ///     static ref TEST_REGEX: Regex = Regex::new("(?P<VariantA>(?P<VariantA_one>[0-9]{5})-(?P<VariantA_two>[a-z]*)?)|(?P<VariantB>some(?P<VariantB_0>thing))")
+    ///     static ref TEST_REGEX: Regex = Regex::new(r"\A(?:(?P<VariantA>(?P<VariantA_one>[0-9]{5})-(?P<VariantA_two>[a-z]*)?)|(?P<VariantB>some(?P<VariantB_0>thing)))\z")
+    ///     static ref TEST_VARIANT_A_REGEX: Regex = Regex::new("(?P<VariantA>(?P<VariantA_one>[0-9]{5})-(?P<VariantA_two>[a-z]*)?)")
+    ///     static ref TEST_VARIANT_B_REGEX: Regex = Regex::new("(?P<VariantB>some(?P<VariantB_0>thing))")
     /// }
     /// ```
+    ///
+    /// The combined regex is anchored to the whole input, so it only
+    /// matches (for `from_regex`) if some variant matches all of it. Since
+    /// regex alternation is leftmost-first, it can't be used to find the
+    /// longest match while searching, so each variant's pattern is also
+    /// compiled on its own (along with an anchored `_FULL_REGEX` version, for
+    /// `from_regex_all`). All variants (including transparent ones) are
+    /// searched, and the leftmost-longest of the overlapping matches are kept.
     fn to_tokens_longest(&self) -> proc_macro2::TokenStream {
         // Combine regex patterns we have into one single pattern
         let ident = self.ident;
         let regex_ident = syn::Ident::new(&format!("{}_REGEX", self.name_shouty()), ident.span());
 
         let mut patterns = Vec::new();
+        let mut regex_defs = Vec::new();
         let mut from_capture_impls = Vec::new();
         let mut from_regex_impls = Vec::new();
-
-        let mut match_locations_patterened = Vec::new();
-        let mut match_locations_transparent = Vec::new();
+        let mut match_candidates_impls = Vec::new();
+        let mut match_overlapping_impls = Vec::new();
+        let mut from_regex_all_impls = Vec::new();
+        let mut search_patterns_impls = Vec::new();
+        let mut find_at_impls = Vec::new();
+        let mut finder_impls = Vec::new();
+        // Per lexer state (`None` for the initial one): its variants'
+        // patterns, and the sections keeping each one's token
+        let mut lex_states: Vec<(Option<String>, Vec<String>, Vec<proc_macro2::TokenStream>)> =
+            Vec::new();
 
         for variant in self.variants.iter() {
             let ident = variant.ident;
@@ -183,34 +517,36 @@ impl<'a> Item<'a> {
                 VariantPattern::Some(pattern_lit) => {
                     let mut pattern = pattern_lit.value();
 
-                    // Get group / field pairs
-                    let groups = captures::from_regex_pattern(&pattern.clone())
-                        .into_iter()
-                        .map(|(group, optional)| {
-                            // Prepend group name with variant name
-                            let new_group = if group.starts_with('_') {
-                                format!("{}{}", ident_str, group)
-                            } else {
-                                format!("{}_{}", ident_str, group)
-                            };
-
-                            pattern = pattern
-                                .replace(&format!("?P<{}>", group), &format!("?P<{}>", new_group));
-
-                            (new_group, optional)
-                        })
-                        .collect::<HashMap<_, _>>();
+                    // Prepend group names with the variant name (invalid
+                    // patterns are kept, to fail in the generated code)
+                    if let Ok(renamed) = from_regex_syntax::map_group_names(&pattern, |group| {
+                        Some(captures::prefixed_group_name(&ident_str, group))
+                    }) {
+                        pattern = renamed;
+                    }
 
-                    // For borrow
-                    let captured_groups = groups.iter().map(|(s, b)| (s.as_str(), *b)).collect();
+                    // Get group / field pairs
+                    let groups = captures::from_regex_pattern(&pattern);
 
-                    // Collect variant patterns
+                    // Collect variant patterns, and compile each on its own
+                    // for searching
                     let ident_str_lit = syn::LitStr::new(&ident_str, ident.span());
-                    patterns.push(format!("(?P<{}>{})", ident_str, pattern));
+                    let pattern = format!("(?P<{}>{})", ident_str, pattern);
+                    let variant_regex_ident = self.variant_regex_ident(variant);
+                    let full_regex_ident = self.variant_full_regex_ident(variant);
+                    regex_defs.push(impl_variant_regexes(
+                        &variant_regex_ident,
+                        &full_regex_ident,
+                        &pattern,
+                    ));
+                    search_patterns_impls.push(quote! {
+                        patterns.push(String::from(#pattern));
+                    });
+                    patterns.push(pattern.clone());
 
                     // Generate a variant specific `__from_regex_capture_x` (will unwrap unless transparent)
                     let (from_capture_fn, from_capture_impl) =
-                        variant.impl_from_capture(&captured_groups, true);
+                        variant.impl_from_capture(&groups, true);
 
                     from_capture_impls.push(from_capture_impl);
                     from_regex_impls.push(quote! {
@@ -223,40 +559,123 @@ impl<'a> Item<'a> {
                         }
                     });
 
-                    match_locations_patterened.push(quote! {
-                        if cap.name(#ident_str_lit).is_some() {
-                            if let Some(value) = Self::#from_capture_fn(&cap) {
-                                ranges.insert_if_empty(range, value);
-                                continue;
-                            }
-                        }
+                    match_candidates_impls.push(
+                        variant.impl_patterned_candidates(&variant_regex_ident, &from_capture_fn),
+                    );
+                    let find_at =
+                        variant.impl_patterned_find_at(&variant_regex_ident, &from_capture_fn);
+                    finder_impls.push(quote! {
+                        from_regex::Finder::new(move |start| #find_at)
                     });
+                    find_at_impls.push(find_at);
+                    match_overlapping_impls.push(
+                        variant.impl_patterned_overlapping(&variant_regex_ident, &from_capture_fn),
+                    );
+                    from_regex_all_impls
+                        .push(variant.impl_patterned_all(&full_regex_ident, &from_capture_fn));
+
+                    if self.attrs.lexer {
+                        let state = variant.attrs.lex_state();
+                        let index = match lex_states.iter().position(|(s, _, _)| *s == state) {
+                            Some(index) => index,
+                            None => {
+                                lex_states.push((state, Vec::new(), Vec::new()));
+                                lex_states.len() - 1
+                            }
+                        };
+                        let (_, patterns, sections) = &mut lex_states[index];
+                        patterns.push(pattern.clone());
+                        sections
+                            .push(variant.impl_lex_token(&variant_regex_ident, &from_capture_fn));
+                    }
                 }
 
                 VariantPattern::Transparent => {
+                    if self.attrs.lexer {
+                        abort!(
+                            ident.span(),
+                            "Transparent variants can't be used in a lexer"
+                        );
+                    }
+
                     let inner = variant.transparent_inner_type().unwrap();
+                    let constructor = variant.impl_transparent_constructor(quote! { inner });
                     from_regex_impls.push(quote! {
                         if let Some(inner) = <#inner>::from_regex(s) {
-                            return Some(Self::#ident(inner));
+                            return Some(#constructor);
                         }
                     });
 
-                    // If we have transparent items to search for, use their search
-                    // functions to generate sibling `SegmentMap`s and use our extension
-                    // trait to keep segments longer than already found
-                    match_locations_transparent.push(quote! {
-                        ranges.merge_only_longest(
-                            <#inner>::match_locations(s).into_iter().map(|(r, v)| {
-                                (r, Self::#ident(v))
-                            })
-                        );
+                    // Transparent items are searched with their own search
+                    // function, and compete with the other variants' matches
+                    match_candidates_impls.push(variant.impl_transparent_candidates(inner));
+                    find_at_impls.push(
+                        variant.impl_transparent_find_at(quote! { <#inner>::find_at(s, start) }),
+                    );
+                    let find_at = variant.impl_transparent_find_at(quote! { inner.find_at(start) });
+                    finder_impls.push(quote! {
+                        {
+                            let mut inner = <#inner>::finder(s);
+                            from_regex::Finder::new(move |start| #find_at)
+                        }
+                    });
+                    match_overlapping_impls.push(variant.impl_transparent_overlapping(inner));
+                    from_regex_all_impls.push(variant.impl_transparent_all(inner));
+                    search_patterns_impls.push(quote! {
+                        patterns.extend(<#inner>::search_patterns()?);
                     });
                 }
 
                 VariantPattern::None => { /* No Op, since we'll never return these from regex */ }
             }
         }
-        let combined_pattern = patterns.join("|");
+        let combined_pattern = format!(r"\A(?:{})\z", patterns.join("|"));
+        let impl_max_match_len = crate::impl_max_match_len(self.attrs.max_len.as_ref());
+        let impl_variant_name = self.impl_variant_name();
+
+        // Lexing searches the state's variants together (anchored at the
+        // current position) for those that can match there, and keeps the
+        // longest of their tokens (or the first, if tied)
+        let impl_lexer = if self.attrs.lexer {
+            let mut state_arms = Vec::new();
+            for (i, (state, patterns, sections)) in lex_states.iter().enumerate() {
+                let patterns_ident = self.lex_patterns_ident(i);
+                regex_defs.push(quote! {
+                    static ref #patterns_ident: from_regex::LexPatterns = from_regex::LexPatterns::new(&[#(#patterns),*]).expect("Failed to compile regex");
+                });
+                let state = match state {
+                    Some(state) => quote! { Some(#state) },
+                    None => quote! { None },
+                };
+                let indices = 0..sections.len();
+                state_arms.push(quote! {
+                    #state => {
+                        for index in #patterns_ident.matching_at(text, pos) {
+                            match index {
+                                #(
+                                    #indices => { #sections }
+                                )*
+                                _ => unreachable!(),
+                            }
+                        }
+                    }
+                });
+            }
+            quote! {
+                impl from_regex::Lexer for #ident {
+                    fn lex_token(text: &str, pos: usize, state: Option<&str>) -> Option<from_regex::Lexeme<Self>> {
+                        let mut best: Option<from_regex::Lexeme<Self>> = None;
+                        match state {
+                            #(#state_arms)*
+                            _ => {}
+                        }
+                        best
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
 
         // Default return for from_regex
         let return_from_regex = self
@@ -266,6 +685,9 @@ impl<'a> Item<'a> {
         quote! {
             from_regex::lazy_static! {
                 static ref #regex_ident: from_regex::Regex = from_regex::Regex::new(#combined_pattern).expect("Failed to compile regex");
+                #(
+                    #regex_defs
+                )*
             }
             impl #ident {
                 #(
@@ -274,30 +696,87 @@ impl<'a> Item<'a> {
             }
             impl from_regex::FromRegex for #ident {
                 fn from_regex(s: &str) -> Option<Self> {
-                    let captures = #regex_ident.captures(s).filter(|cap| cap[0].len() == s.len());
+                    let captures = #regex_ident.captures(s);
                     #(
                         #from_regex_impls
                     )*
                     #return_from_regex
                 }
 
+                fn from_regex_all(s: &str) -> Vec<Self> {
+                    let mut all = Vec::new();
+                    #(
+                        #from_regex_all_impls
+                    )*
+                    all
+                }
+
                 fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
-                    use from_regex::TextMap;
-                    let mut ranges = from_regex::SegmentMap::new();
-                    for cap in #regex_ident.captures_iter(s) {
-                        let range = cap.get(0).unwrap().range();
-                        #(
-                            #match_locations_patterened
-                        )*
-                    }
+                    Self::match_locations_in(s, 0..s.len())
+                }
+
+                // The leftmost (then longest) of the variants' next matches.
+                // Searching resumes after it, so that matches of other
+                // variants overlapping it don't hide those that follow.
+                fn find_at(s: &str, start: usize) -> Option<from_regex::Candidate<Self>> {
+                    let mut best: Option<from_regex::Candidate<Self>> = None;
+                    #(
+                        if let Some(candidate) = #find_at_impls {
+                            let better = match &best {
+                                Some(best) => {
+                                    from_regex::OverlapPolicy::LeftmostLongest.compare(&candidate, best)
+                                        == std::cmp::Ordering::Less
+                                }
+                                None => true,
+                            };
+                            if better {
+                                best = Some(candidate);
+                            }
+                        }
+                    )*
+                    best
+                }
+
+                // Each variant's next match is only searched for again once
+                // it overlaps a match kept, so searching stays linear
+                fn finder<'s>(s: &'s str) -> from_regex::Finder<'s, Self>
+                where
+                    Self: 's,
+                {
+                    from_regex::Finder::leftmost_longest(vec![#(#finder_impls),*])
+                }
+
+                fn match_candidates(s: &str) -> Vec<from_regex::Candidate<Self>> {
+                    let mut candidates = Vec::new();
+                    #(
+                        #match_candidates_impls
+                    )*
+                    candidates
+                }
 
+                fn match_all_overlapping(s: &str) -> Vec<(std::ops::Range<usize>, Self)> {
+                    let mut hits = Vec::new();
                     #(
-                        #match_locations_transparent
+                        #match_overlapping_impls
                     )*
+                    hits.sort_by_key(|(range, _)| range.start);
+                    hits
+                }
 
-                    ranges
+                fn search_patterns() -> Option<Vec<String>> {
+                    let mut patterns = Vec::new();
+                    #(
+                        #search_patterns_impls
+                    )*
+                    Some(patterns)
                 }
+
+                #impl_max_match_len
+
+                #impl_variant_name
             }
+
+            #impl_lexer
         }
     }
 
@@ -306,34 +785,40 @@ impl<'a> Item<'a> {
     ///
     fn to_tokens_first(&self) -> proc_macro2::TokenStream {
         let ident = self.ident;
-
-        let enum_name_shouty = self.name_shouty();
+        let impl_max_match_len = crate::impl_max_match_len(self.attrs.max_len.as_ref());
+        let impl_variant_name = self.impl_variant_name();
+        if self.attrs.lexer {
+            abort!(
+                ident.span(),
+                "Lexers need `match_mode = \"longest\"`, to take the longest token"
+            );
+        }
 
         let mut regex_defs = Vec::new();
         let mut from_capture_impls = Vec::new();
         let mut from_regex_impls = Vec::new();
-        let mut match_locations_impls = Vec::new();
+        let mut match_candidates_impls = Vec::new();
+        let mut match_overlapping_impls = Vec::new();
+        let mut from_regex_all_impls = Vec::new();
+        let mut search_patterns_impls = Vec::new();
 
         for variant in self.variants.iter() {
-            let ident = variant.ident;
-
             // If a patterned variant, collect it's
             match &variant.attrs.pattern {
                 VariantPattern::Some(pattern_lit) => {
                     let pattern = pattern_lit.value();
 
-                    let regex_ident = syn::Ident::new(
-                        &format!(
-                            "{}_{}_REGEX",
-                            enum_name_shouty,
-                            variant.ident.to_string().TO_SHOUTY_SNEK_CASE()
-                        ),
-                        self.ident.span(),
-                    );
-
-                    // Generate a regex constant for this variant only
-                    regex_defs.push(quote! {
-                        static ref #regex_ident: from_regex::Regex = from_regex::Regex::new(#pattern).expect("Failed to compile regex");
+                    let regex_ident = self.variant_regex_ident(variant);
+                    let full_regex_ident = self.variant_full_regex_ident(variant);
+
+                    // Generate regex constants for this variant only
+                    regex_defs.push(impl_variant_regexes(
+                        &regex_ident,
+                        &full_regex_ident,
+                        &pattern,
+                    ));
+                    search_patterns_impls.push(quote! {
+                        patterns.push(String::from(#pattern));
                     });
 
                     // Generate a variant specific `__from_regex_capture_x`
@@ -344,35 +829,36 @@ impl<'a> Item<'a> {
 
                     // Add a section for `from_regex` calling this variant's
                     // conversion method
-                    from_regex_impls.push(quote! {                    
-                        if let Some(captures) = #regex_ident.captures(s).filter(|cap| cap[0].len() == s.len()) {
+                    from_regex_impls.push(quote! {
+                        if let Some(captures) = #full_regex_ident.captures(s) {
                             if let Some(value) = Self::#from_capture_fn(&captures) {
                                 return Some(value);
                             }
                         }
                     });
 
-                    match_locations_impls.push(quote! {
-                        for cap in #regex_ident.captures_iter(s) {
-                            if let Some(value) = Self::#from_capture_fn(&cap) {
-                                ranges.insert_if_empty(cap.get(0).unwrap().range(), value);
-                            }
-                        }
-                    });
+                    match_candidates_impls
+                        .push(variant.impl_patterned_candidates(&regex_ident, &from_capture_fn));
+                    match_overlapping_impls
+                        .push(variant.impl_patterned_overlapping(&regex_ident, &from_capture_fn));
+                    from_regex_all_impls
+                        .push(variant.impl_patterned_all(&full_regex_ident, &from_capture_fn));
                 }
 
                 VariantPattern::Transparent => {
                     let inner = variant.transparent_inner_type().unwrap();
+                    let constructor = variant.impl_transparent_constructor(quote! { inner });
                     from_regex_impls.push(quote! {
                         if let Some(inner) = <#inner>::from_regex(s) {
-                            return Some(Self::#ident(inner));
+                            return Some(#constructor);
                         }
                     });
 
-                    match_locations_impls.push(quote! {
-                        for (range, value) in <#inner>::match_locations(s) {
-                            ranges.insert_if_empty(range, Self::#ident(value));
-                        }
+                    match_candidates_impls.push(variant.impl_transparent_candidates(inner));
+                    match_overlapping_impls.push(variant.impl_transparent_overlapping(inner));
+                    from_regex_all_impls.push(variant.impl_transparent_all(inner));
+                    search_patterns_impls.push(quote! {
+                        patterns.extend(<#inner>::search_patterns()?);
                     });
                 }
 
@@ -404,35 +890,113 @@ impl<'a> Item<'a> {
                     #return_from_regex
                 }
 
+                fn from_regex_all(s: &str) -> Vec<Self> {
+                    let mut all = Vec::new();
+                    #(
+                        #from_regex_all_impls
+                    )*
+                    all
+                }
+
                 fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
-                    use from_regex::TextMap;
-                    let mut ranges = from_regex::SegmentMap::new();
+                    from_regex::OverlapPolicy::FirstFound.resolve(Self::match_candidates(s))
+                }
 
+                // Every variant's matches are kept before the next's, so the
+                // entire string is searched
+                fn match_locations_in(
+                    s: &str,
+                    range: std::ops::Range<usize>,
+                ) -> from_regex::SegmentMap<usize, Self>
+                where
+                    Self: Clone + Eq,
+                {
+                    let mut locations = from_regex::SegmentMap::new();
+                    for (segment, value) in Self::match_locations(s) {
+                        if let std::ops::Bound::Included(start) = std::ops::RangeBounds::start_bound(&segment) {
+                            if range.contains(start) {
+                                locations.insert_if_empty(segment, value);
+                            }
+                        }
+                    }
+                    locations
+                }
+
+                fn match_candidates(s: &str) -> Vec<from_regex::Candidate<Self>> {
+                    let mut candidates = Vec::new();
                     #(
-                        #match_locations_impls
+                        #match_candidates_impls
                     )*
+                    candidates
+                }
 
-                    ranges
+                fn match_all_overlapping(s: &str) -> Vec<(std::ops::Range<usize>, Self)> {
+                    let mut hits = Vec::new();
+                    #(
+                        #match_overlapping_impls
+                    )*
+                    hits.sort_by_key(|(range, _)| range.start);
+                    hits
                 }
+
+                fn search_patterns() -> Option<Vec<String>> {
+                    let mut patterns = Vec::new();
+                    #(
+                        #search_patterns_impls
+                    )*
+                    Some(patterns)
+                }
+
+                #impl_max_match_len
+
+                #impl_variant_name
             }
         }
     }
 }
 
+/// Generate definitions for a variant's search regex and its counterpart
+/// anchored to the entire input (for `from_regex`), since `Regex::captures`
+/// returns the leftmost-first match, which may not be the one spanning the
+/// whole input
+fn impl_variant_regexes(
+    regex_ident: &syn::Ident,
+    full_regex_ident: &syn::Ident,
+    pattern: &str,
+) -> proc_macro2::TokenStream {
+    let full_pattern = format!(r"\A(?:{})\z", pattern);
+    quote! {
+        static ref #regex_ident: from_regex::Regex = from_regex::Regex::new(#pattern).expect("Failed to compile regex");
+        static ref #full_regex_ident: from_regex::Regex = from_regex::Regex::new(#full_pattern).expect("Failed to compile regex");
+    }
+}
+
 pub struct Variant<'a> {
     ident: &'a syn::Ident,
     attrs: VariantAttributes,
     fields: &'a syn::Fields,
+    transparent: Option<Transparent<'a>>,
 }
 
 pub struct VariantAttributes {
     pattern: VariantPattern,
-    default: bool,
+    default: Option<VariantDefault>,
+    priority: i64,
+    lex: Option<LexAttributes>,
+    display: Option<syn::LitStr>,
+    // Full path of the file the pattern was read from (if any)
+    pattern_file: Option<String>,
 }
 impl VariantAttributes {
     fn is_transparent(&self) -> bool {
         matches!(self.pattern, VariantPattern::Transparent)
     }
+
+    /// The lexer state this variant is active in (`None` for the initial
+    /// state)
+    fn lex_state(&self) -> Option<String> {
+        self.lex.as_ref()?.state.as_ref().map(syn::LitStr::value)
+    }
 }
 
 enum VariantPattern {
@@ -441,14 +1005,65 @@ enum VariantPattern {
     Transparent,
 }
 
+// How a variant is used by a lexer
+#[derive(Default)]
+struct LexAttributes {
+    // Consume tokens of this variant without returning them
+    skip: bool,
+    // The only state this variant is active in (the initial state if unset)
+    state: Option<syn::LitStr>,
+    // Enter a state (`Some`) or return to the previous one (`None`) after
+    // this variant
+    transition: Option<Option<syn::LitStr>>,
+}
+
+// How the default variant is constructed from unmatched input
+enum VariantDefault {
+    // From its fields, with the input in the `input` field (if any)
+    Fields,
+    // By calling an expression (e.g. a function or closure) with the input
+    Constructor(Box<syn::Expr>),
+}
+
+/// Paths `String` can be named by
+const STRING_PATHS: &[&str] = &["String", "std::string::String", "alloc::string::String"];
+
+/// Whether `path` names `String` (possibly with its full path)
+fn is_string(path: &syn::Path) -> bool {
+    let segments = path
+        .segments
+        .iter()
+        .map(|segment| match segment.arguments {
+            syn::PathArguments::None => Some(segment.ident.to_string()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    match segments {
+        Some(segments) => STRING_PATHS.contains(&segments.join("::").as_str()),
+        None => false,
+    }
+}
+
 const VARIANT_ATTRIBUTE_PATTERN: &str = "pattern";
+const VARIANT_ATTRIBUTE_PATTERN_FILE: &str = "pattern_file";
 const VARIANT_ATTRIBUTE_DEFAULT: &str = "default";
 const VARIANT_ATTRIBUTE_TRANSPARENT: &str = "transparent";
+const VARIANT_ATTRIBUTE_PRIORITY: &str = "priority";
+const VARIANT_ATTRIBUTE_DISPLAY: &str = "display";
+const VARIANT_ATTRIBUTE_SKIP: &str = "skip";
+const VARIANT_ATTRIBUTE_STATE: &str = "state";
+const VARIANT_ATTRIBUTE_PUSH: &str = "push";
+const VARIANT_ATTRIBUTE_POP: &str = "pop";
+const FIELD_ATTRIBUTE_INPUT: &str = "input";
 
 impl<'a> From<&'a [syn::Attribute]> for VariantAttributes {
     fn from(attrs: &'a [syn::Attribute]) -> Self {
         let mut pattern = VariantPattern::None;
-        let mut default = false;
+        let mut default = None;
+        let mut priority = 0;
+        let mut lex: Option<LexAttributes> = None;
+        let mut display = None;
+        let mut pattern_file = None;
         for attr in attrs {
             if let syn::Meta::List(list) = attr.parse_meta().expect("failed to parse attr meta") {
                 if list.path.is_ident(crate::ATTRIBUTE) {
@@ -460,18 +1075,88 @@ impl<'a> From<&'a [syn::Attribute]> for VariantAttributes {
                                     path,
                                     lit: syn::Lit::Str(lit),
                                     ..
-                                }) => {
-                                    if path.is_ident(VARIANT_ATTRIBUTE_PATTERN) {
-                                        match pattern {
-                                            VariantPattern::None => pattern = VariantPattern::Some(lit),
-                                            VariantPattern::Some(_) => abort!(attr_span, "Pattern already defined on this variant"),
-                                            VariantPattern::Transparent => abort!(attr_span, "Variants can only have a pattern or be transparent (not both)"),
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_PATTERN)
+                                    || path.is_ident(VARIANT_ATTRIBUTE_PATTERN_FILE) =>
+                                {
+                                    let lit = if path.is_ident(VARIANT_ATTRIBUTE_PATTERN_FILE) {
+                                        let (lit, file) = crate::pattern_file::read(&lit)
+                                            .unwrap_or_else(|err| abort!(err.span(), "{}", err));
+                                        pattern_file = Some(file);
+                                        lit
+                                    } else {
+                                        lit
+                                    };
+                                    match pattern {
+                                        VariantPattern::None => pattern = VariantPattern::Some(lit),
+                                        VariantPattern::Some(_) => abort!(attr_span, "Pattern already defined on this variant"),
+                                        VariantPattern::Transparent => abort!(attr_span, "Variants can only have a pattern or be transparent (not both)"),
+                                    }
+                                }
+                                syn::Meta::NameValue(syn::MetaNameValue {
+                                    path,
+                                    lit: syn::Lit::Str(lit),
+                                    ..
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_DEFAULT) => {
+                                    default = match lit.parse() {
+                                        Ok(expr) => {
+                                            Some(VariantDefault::Constructor(Box::new(expr)))
                                         }
+                                        Err(err) => abort!(
+                                            lit.span(),
+                                            "Invalid default expression: {}",
+                                            err
+                                        ),
+                                    };
+                                }
+                                syn::Meta::NameValue(syn::MetaNameValue {
+                                    path,
+                                    lit: syn::Lit::Str(lit),
+                                    ..
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_DISPLAY) => {
+                                    display = Some(lit);
+                                }
+                                syn::Meta::NameValue(syn::MetaNameValue {
+                                    path,
+                                    lit: syn::Lit::Str(lit),
+                                    ..
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_STATE) => {
+                                    lex.get_or_insert_with(Default::default).state = Some(lit);
+                                }
+                                syn::Meta::NameValue(syn::MetaNameValue {
+                                    path,
+                                    lit: syn::Lit::Str(lit),
+                                    ..
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_PUSH) => {
+                                    let lex = lex.get_or_insert_with(Default::default);
+                                    if lex.transition.is_some() {
+                                        abort!(attr_span, "Variants can only `push` or `pop` once");
                                     }
+                                    lex.transition = Some(Some(lit));
+                                }
+                                syn::Meta::NameValue(syn::MetaNameValue {
+                                    path,
+                                    lit: syn::Lit::Int(lit),
+                                    ..
+                                }) if path.is_ident(VARIANT_ATTRIBUTE_PRIORITY) => {
+                                    priority = match lit.base10_parse() {
+                                        Ok(value) => value,
+                                        Err(err) => abort!(lit.span(), "Invalid priority: {}", err),
+                                    };
                                 }
                                 syn::Meta::Path(path) => {
-                                    if path.is_ident(VARIANT_ATTRIBUTE_DEFAULT) {
-                                        default = true;
+                                    if path.is_ident(VARIANT_ATTRIBUTE_SKIP) {
+                                        lex.get_or_insert_with(Default::default).skip = true;
+                                    } else if path.is_ident(VARIANT_ATTRIBUTE_POP) {
+                                        let lex = lex.get_or_insert_with(Default::default);
+                                        if lex.transition.is_some() {
+                                            abort!(
+                                                attr_span,
+                                                "Variants can only `push` or `pop` once"
+                                            );
+                                        }
+                                        lex.transition = Some(None);
+                                    } else if path.is_ident(VARIANT_ATTRIBUTE_DEFAULT) {
+                                        default = Some(VariantDefault::Fields);
                                     } else if path.is_ident(VARIANT_ATTRIBUTE_TRANSPARENT) {
                                         match pattern {
                                             VariantPattern::None => pattern = VariantPattern::Transparent,
@@ -488,7 +1173,14 @@ impl<'a> From<&'a [syn::Attribute]> for VariantAttributes {
             }
         }
 
-        Self { pattern, default }
+        Self {
+            pattern,
+            default,
+            priority,
+            lex,
+            display,
+            pattern_file,
+        }
     }
 }
 
@@ -497,22 +1189,20 @@ impl<'a> Variant<'a> {
         let ident = &variant.ident;
         let attrs = VariantAttributes::from(variant.attrs.as_ref());
         let fields = &variant.fields;
-        if attrs.is_transparent()
-            && !matches!(fields, syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed, ..}) if unnamed.len() == 1)
-        {
-            abort!(
-                ident.span(),
-                "The `transparent` attribute is only available for single element tuple structs"
-            );
-        }
+        let transparent = if attrs.is_transparent() {
+            Some(Transparent::new(ident, fields))
+        } else {
+            None
+        };
         Self {
             ident,
             attrs,
             fields,
+            transparent,
         }
     }
 
-    fn from_capture_fn_ident(&self) -> syn::Ident {
+    fn capture_fn_ident(&self) -> syn::Ident {
         syn::Ident::new(
             &format!(
                 "__from_regex_capture_{}",
@@ -533,7 +1223,7 @@ impl<'a> Variant<'a> {
         } else {
             None
         };
-        let fn_ident = self.from_capture_fn_ident();
+        let fn_ident = self.capture_fn_ident();
 
         let case_attr = if prefixed {
             quote! {
@@ -548,7 +1238,7 @@ impl<'a> Variant<'a> {
             syn::Fields::Named(syn::FieldsNamed { .. }) => {
                 let (field_names, field_statements) = crate::captures::impl_fields_from_capture(
                     captured_groups,
-                    &self.fields,
+                    self.fields,
                     prefix.as_deref(),
                 );
 
@@ -563,7 +1253,7 @@ impl<'a> Variant<'a> {
             syn::Fields::Unnamed(syn::FieldsUnnamed { .. }) => {
                 let (assigned_names, field_statements) = crate::captures::impl_fields_from_capture(
                     captured_groups,
-                    &self.fields,
+                    self.fields,
                     prefix.as_deref(),
                 );
 
@@ -587,11 +1277,337 @@ impl<'a> Variant<'a> {
         (fn_ident, implementation)
     }
 
+    /// Generate a section for `match_candidates` collecting every match of
+    /// this variant's regex
+    fn impl_patterned_candidates(
+        &self,
+        regex_ident: &syn::Ident,
+        from_capture_fn: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        let priority = self.attrs.priority;
+        quote! {
+            for cap in #regex_ident.captures_iter(s) {
+                if let Some(value) = Self::#from_capture_fn(&cap) {
+                    candidates.push(from_regex::Candidate {
+                        range: cap.get(0).unwrap().range(),
+                        priority: #priority,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Generate an expression finding this variant's first match starting at
+    /// or after `start` (for `find_at`)
+    fn impl_patterned_find_at(
+        &self,
+        regex_ident: &syn::Ident,
+        from_capture_fn: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        let priority = self.attrs.priority;
+        quote! {
+            from_regex::captures_from(&#regex_ident, s, start).find_map(|cap| {
+                Self::#from_capture_fn(&cap).map(|value| from_regex::Candidate {
+                    range: cap.get(0).unwrap().range(),
+                    priority: #priority,
+                    value,
+                })
+            })
+        }
+    }
+
+    /// Generate an expression converting the inner type's next match, found
+    /// by `find`, to this variant's (with its priority), for `find_at`
+    fn impl_transparent_find_at(&self, find: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let constructor = self.impl_transparent_constructor(quote! { candidate.value });
+        let priority = self.attrs.priority;
+        quote! {
+            #find.map(|candidate| from_regex::Candidate {
+                range: candidate.range,
+                priority: #priority,
+                value: #constructor,
+            })
+        }
+    }
+
+    /// Generate a section for `match_candidates` collecting the inner type's
+    /// candidates (with this variant's priority)
+    fn impl_transparent_candidates(&self, inner: &syn::Type) -> proc_macro2::TokenStream {
+        let constructor = self.impl_transparent_constructor(quote! { candidate.value });
+        let priority = self.attrs.priority;
+        quote! {
+            candidates.extend(<#inner>::match_candidates(s).into_iter().map(|candidate| {
+                from_regex::Candidate {
+                    range: candidate.range,
+                    priority: #priority,
+                    value: #constructor,
+                }
+            }));
+        }
+    }
+
+    /// Generate a section for `match_all_overlapping` collecting every match
+    /// of this variant's regex, at every position
+    fn impl_patterned_overlapping(
+        &self,
+        regex_ident: &syn::Ident,
+        from_capture_fn: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        quote! {
+            for cap in from_regex::captures_overlapping(&#regex_ident, s) {
+                if let Some(value) = Self::#from_capture_fn(&cap) {
+                    hits.push((cap.get(0).unwrap().range(), value));
+                }
+            }
+        }
+    }
+
+    /// Generate a section for `match_all_overlapping` collecting every match
+    /// of the inner type
+    fn impl_transparent_overlapping(&self, inner: &syn::Type) -> proc_macro2::TokenStream {
+        let constructor = self.impl_transparent_constructor(quote! { value });
+        quote! {
+            hits.extend(<#inner>::match_all_overlapping(s).into_iter().map(|(range, value)| {
+                (range, #constructor)
+            }));
+        }
+    }
+
+    /// Generate a section for `from_regex_all` adding this variant if it
+    /// matches the entire input
+    fn impl_patterned_all(
+        &self,
+        full_regex_ident: &syn::Ident,
+        from_capture_fn: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        quote! {
+            if let Some(cap) = #full_regex_ident.captures(s) {
+                if let Some(value) = Self::#from_capture_fn(&cap) {
+                    all.push(value);
+                }
+            }
+        }
+    }
+
+    /// Generate a section for `from_regex_all` adding every way the inner
+    /// type matches the entire input
+    fn impl_transparent_all(&self, inner: &syn::Type) -> proc_macro2::TokenStream {
+        let constructor = self.impl_transparent_constructor(quote! { inner });
+        quote! {
+            all.extend(<#inner>::from_regex_all(s).into_iter().map(|inner| #constructor));
+        }
+    }
+
+    /// Generate a section for `Lexer::lex_token` keeping this variant's token
+    /// (known to match at `pos`) if it's longer than the best so far
+    fn impl_lex_token(
+        &self,
+        regex_ident: &syn::Ident,
+        from_capture_fn: &syn::Ident,
+    ) -> proc_macro2::TokenStream {
+        let lex = self.attrs.lex.as_ref();
+        let skip = lex.is_some_and(|lex| lex.skip);
+        let transition = match lex.and_then(|lex| lex.transition.as_ref()) {
+            Some(Some(state)) => quote! { from_regex::Transition::Push(#state) },
+            Some(None) => quote! { from_regex::Transition::Pop },
+            None => quote! { from_regex::Transition::Stay },
+        };
+
+        // Since the variant matches at `pos`, its leftmost match starts there
+        quote! {
+            if let Some(cap) = #regex_ident.captures_at(text, pos) {
+                let len = cap.get(0).unwrap().end() - pos;
+                if !matches!(&best, Some(best) if best.len >= len) {
+                    if let Some(value) = Self::#from_capture_fn(&cap) {
+                        best = Some(from_regex::Lexeme {
+                            len,
+                            value,
+                            skip: #skip,
+                            transition: #transition,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate an expression constructing this (default) variant from the
+    /// unmatched input `s`
+    fn impl_default_constructor(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        let input = match &self.attrs.default {
+            Some(VariantDefault::Constructor(constructor)) => return quote! { (#constructor)(s) },
+            Some(VariantDefault::Fields) => self.input_field(),
+            None => unreachable!("variant is not the default"),
+        };
+
+        let values = self.fields.iter().enumerate().map(|(i, field)| {
+            let ty = &field.ty;
+            if Some(i) == input {
+                quote! { s.into() }
+            } else {
+                quote! { <#ty>::default() }
+            }
+        });
+        match self.fields {
+            syn::Fields::Named(syn::FieldsNamed { named, .. }) => {
+                let names = named.iter().map(|field| &field.ident);
+                quote! { Self::#ident { #( #names: #values ),* } }
+            }
+            syn::Fields::Unnamed(_) => quote! { Self::#ident ( #( #values ),* ) },
+            syn::Fields::Unit => quote! { Self::#ident },
+        }
+    }
+
+    /// The index of the field receiving the unmatched input of a default
+    /// variant: the one marked `#[from_regex(input)]`, or the only field of
+    /// a tuple variant if it's a `String`
+    fn input_field(&self) -> Option<usize> {
+        let mut input = None;
+        for (i, field) in self.fields.iter().enumerate() {
+            for meta in crate::Attributes::from(field.attrs.as_ref()) {
+                if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = meta {
+                    if path.is_ident(FIELD_ATTRIBUTE_INPUT) {
+                        if input.is_some() {
+                            abort!(path.span(), "Only one field can receive the input");
+                        }
+                        input = Some(i);
+                    }
+                }
+            }
+        }
+
+        input.or_else(|| match self.fields {
+            syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed, .. }) if unnamed.len() == 1 => {
+                match &unnamed[0].ty {
+                    syn::Type::Path(syn::TypePath { qself: None, path }) if is_string(path) => {
+                        Some(0)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+    }
+
     fn transparent_inner_type(&self) -> Option<&syn::Type> {
-        if let syn::Fields::Unnamed(syn::FieldsUnnamed { unnamed, .. }) = self.fields {
-            unnamed.first().map(|f| &f.ty)
-        } else {
+        self.transparent.as_ref().map(Transparent::inner_type)
+    }
+
+    /// Generate an expression constructing this (transparent) variant from
+    /// the inner value `value`
+    fn impl_transparent_constructor(
+        &self,
+        value: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        self.transparent
+            .as_ref()
+            .expect("variant is not transparent")
+            .constructor(quote! { Self::#ident }, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Item;
+
+    /// The error deriving `item` gives, if any
+    fn error(item: &str) -> Option<String> {
+        let input = syn::parse_str::<syn::DeriveInput>(item).unwrap();
+        let variants = match &input.data {
+            syn::Data::Enum(data) => data.variants.iter(),
+            _ => unreachable!(),
+        };
+        Item::try_new(&input.ident, &input.attrs, variants)
+            .err()
+            .map(|err| err.to_string())
+    }
+
+    #[test]
+    fn dialect_errors() {
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<A>x)")]
+                #[from_regex(default_dialect = "a")]
+                enum E {
+                    #[from_regex(pattern = "x")]
+                    A,
+                }"#
+            )
+            .as_deref(),
+            Some("Enums can't have a default dialect, since they parse with their variants' patterns")
+        );
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<B>x)")]
+                enum E {
+                    #[from_regex(pattern = "x")]
+                    A,
+                }"#
+            )
+            .as_deref(),
+            Some("Dialect `a` can't construct `E`: The pattern has no group for any variant")
+        );
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<A>(?P<A_x>x))|(?P<B>y)")]
+                enum E {
+                    #[from_regex(pattern = "(?P<x>x)")]
+                    A { x: String },
+                    #[from_regex(pattern = "y")]
+                    B,
+                }"#
+            ),
             None
-        }
+        );
+    }
+
+    fn ambiguity_tokens(input: &str) -> String {
+        let input = syn::parse_str::<syn::DeriveInput>(input).unwrap();
+        let variants = match &input.data {
+            syn::Data::Enum(data) => data.variants.iter(),
+            _ => unreachable!(),
+        };
+        Item::new(&input.ident, &input.attrs, variants)
+            .check_ambiguity()
+            .to_string()
+    }
+
+    #[test]
+    fn ambiguity_warnings() {
+        // Warnings are the default in longest mode
+        let warning = ambiguity_tokens(
+            r#"enum Token {
+                #[from_regex(pattern = "[a-z]+")]
+                Identifier,
+                #[from_regex(pattern = "if")]
+                Keyword,
+            }"#,
+        );
+        assert!(warning.contains("deprecated"));
+        assert!(warning.contains("Variants `Identifier` and `Keyword` of `Token` can both match"));
+
+        // Unless allowed, or settled by priority
+        assert!(ambiguity_tokens(
+            r#"#[from_regex(ambiguity = "allow")]
+            enum Token {
+                #[from_regex(pattern = "[a-z]+")]
+                Identifier,
+                #[from_regex(pattern = "if")]
+                Keyword,
+            }"#,
+        )
+        .is_empty());
+        assert!(ambiguity_tokens(
+            r#"enum Token {
+                #[from_regex(pattern = "[a-z]+")]
+                Identifier,
+                #[from_regex(pattern = "if", priority = 1)]
+                Keyword,
+            }"#,
+        )
+        .is_empty());
     }
 }
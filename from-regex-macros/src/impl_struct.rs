@@ -1,38 +1,157 @@
 use heck::ShoutySnekCase;
-use quote::quote;
+use proc_macro_error::abort;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::dialect::Dialect;
+use crate::transparent::Transparent;
 
 pub struct Item<'a> {
     ident: &'a syn::Ident,
     attrs: ItemAttributes,
     fields: &'a syn::Fields,
+    dialects: Vec<Dialect>,
 }
 
 pub struct ItemAttributes {
-    pattern: syn::LitStr,
+    pattern: StructPattern,
+    validate: Option<syn::Path>,
+    max_len: Option<syn::LitInt>,
+    display: Option<syn::LitStr>,
+    default_dialect: Option<syn::LitStr>,
+    // Full path of the file the pattern was read from (if any)
+    pattern_file: Option<String>,
+}
+
+enum StructPattern {
+    Some(syn::LitStr),
+    Transparent,
+    // Assembled from the fields' patterns, joined by the separator (if any)
+    Sequence(Option<syn::LitStr>),
 }
 
 const ITEM_ATTRIBUTE_PATTERN: &str = "pattern";
+const ITEM_ATTRIBUTE_PATTERN_FILE: &str = "pattern_file";
+const ITEM_ATTRIBUTE_TRANSPARENT: &str = "transparent";
+const ITEM_ATTRIBUTE_VALIDATE: &str = "validate";
+const ITEM_ATTRIBUTE_SEPARATOR: &str = "separator";
+const ITEM_ATTRIBUTE_MAX_LEN: &str = "max_len";
+const ITEM_ATTRIBUTE_DISPLAY: &str = "display";
+const ITEM_ATTRIBUTE_DEFAULT_DIALECT: &str = "default_dialect";
+const FIELD_ATTRIBUTE_PATTERN: &str = "pattern";
+
+/// Standard types a field can have, which don't implement `FromRegex` (so
+/// need a `pattern` in a sequence)
+const STD_TYPES: &[&str] = &[
+    "String", "str", "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+    "i32", "i64", "i128", "isize", "f32", "f64", "Option", "Vec",
+];
+
+/// Whether `ty` is one of [`STD_TYPES`]
+fn is_std_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => matches!(
+            path.segments.last(),
+            Some(segment) if STD_TYPES.iter().any(|std| segment.ident == std)
+        ),
+        syn::Type::Reference(_) => true,
+        _ => false,
+    }
+}
 
 impl<'a> From<&'a [syn::Attribute]> for ItemAttributes {
     fn from(attrs: &'a [syn::Attribute]) -> Self {
         let mut pattern = None;
+        let mut transparent = None;
+        let mut validate = None;
+        let mut separator = None;
+        let mut max_len = None;
+        let mut display = None;
+        let mut default_dialect = None;
+        let mut pattern_file = None;
 
         for meta in crate::Attributes::from(attrs) {
-            if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
-                path,
-                lit: syn::Lit::Str(lit),
-                ..
-            })) = meta
-            {
-                if path.is_ident(ITEM_ATTRIBUTE_PATTERN) {
-                    pattern = Some(lit);
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(lit),
+                    ..
+                })) => {
+                    if path.is_ident(ITEM_ATTRIBUTE_PATTERN) {
+                        if pattern_file.is_some() {
+                            abort!(
+                                lit.span(),
+                                "Structs can only have a pattern or a pattern file (not both)"
+                            );
+                        }
+                        pattern = Some(lit);
+                    } else if path.is_ident(ITEM_ATTRIBUTE_PATTERN_FILE) {
+                        if pattern.is_some() {
+                            abort!(
+                                lit.span(),
+                                "Structs can only have a pattern or a pattern file (not both)"
+                            );
+                        }
+                        let (lit, file) = crate::pattern_file::read(&lit)
+                            .unwrap_or_else(|err| abort!(err.span(), "{}", err));
+                        pattern = Some(lit);
+                        pattern_file = Some(file);
+                    } else if path.is_ident(ITEM_ATTRIBUTE_DISPLAY) {
+                        display = Some(lit);
+                    } else if path.is_ident(ITEM_ATTRIBUTE_DEFAULT_DIALECT) {
+                        default_dialect = Some(lit);
+                    } else if path.is_ident(ITEM_ATTRIBUTE_SEPARATOR) {
+                        separator = Some(lit);
+                    } else if path.is_ident(ITEM_ATTRIBUTE_VALIDATE) {
+                        match lit.parse() {
+                            Ok(path) => validate = Some(path),
+                            Err(err) => abort!(lit.span(), "Invalid validation function: {}", err),
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Int(lit),
+                    ..
+                })) if path.is_ident(ITEM_ATTRIBUTE_MAX_LEN) => {
+                    max_len = Some(lit);
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path))
+                    if path.is_ident(ITEM_ATTRIBUTE_TRANSPARENT) =>
+                {
+                    transparent = Some(path);
                 }
+                _ => {}
             }
         }
 
-        let pattern = pattern.expect("Regex pattern must be present");
+        let pattern = match (pattern, transparent) {
+            (Some(pattern), None) => StructPattern::Some(pattern),
+            (None, Some(_)) => StructPattern::Transparent,
+            (Some(_), Some(path)) => abort!(
+                path,
+                "Structs can only have a pattern or be transparent (not both)"
+            ),
+            (None, None) => StructPattern::Sequence(separator.take()),
+        };
+        if let Some(separator) = separator {
+            abort!(
+                separator.span(),
+                "Only structs without a pattern (built from their fields) can have a separator"
+            );
+        }
+        if let (StructPattern::Some(lit), Some(_)) = (&pattern, &validate) {
+            abort!(lit.span(), "Only transparent structs can be validated");
+        }
 
-        Self { pattern }
+        Self {
+            pattern,
+            validate,
+            max_len,
+            display,
+            default_dialect,
+            pattern_file,
+        }
     }
 }
 
@@ -42,31 +161,94 @@ impl<'a> Item<'a> {
         attrs: &'a [syn::Attribute],
         fields: &'a syn::Fields,
     ) -> Self {
-        Self {
+        Self::try_new(ident, attrs, fields).unwrap_or_else(|err| abort!(err.span(), "{}", err))
+    }
+
+    /// [`Item::new`], returning errors with its dialects (rather than
+    /// aborting)
+    fn try_new(
+        ident: &'a syn::Ident,
+        attrs: &'a [syn::Attribute],
+        fields: &'a syn::Fields,
+    ) -> syn::Result<Self> {
+        let (attrs, dialects) = crate::dialect::split(attrs)?;
+        let mut attrs = ItemAttributes::from(attrs.as_slice());
+
+        // Without a pattern of its own, a struct parses (and is searched for)
+        // with its default dialect, or else its first
+        if !dialects.is_empty() || attrs.default_dialect.is_some() {
+            match &attrs.pattern {
+                StructPattern::Sequence(None) => {}
+                StructPattern::Transparent => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "Transparent structs can't have dialects",
+                    ))
+                }
+                _ => {
+                    if let Some(default) = &attrs.default_dialect {
+                        return Err(syn::Error::new(
+                            default.span(),
+                            "Only structs without a pattern of their own can have a default dialect",
+                        ));
+                    }
+                }
+            }
+        }
+        if let StructPattern::Sequence(None) = &attrs.pattern {
+            let default = match &attrs.default_dialect {
+                Some(name) => Some(
+                    dialects
+                        .iter()
+                        .find(|dialect| dialect.name.value() == name.value())
+                        .ok_or_else(|| {
+                            syn::Error::new(
+                                name.span(),
+                                format!("No dialect named `{}`", name.value()),
+                            )
+                        })?,
+                ),
+                None => dialects.first(),
+            };
+            if let Some(default) = default {
+                attrs.pattern = StructPattern::Some(default.pattern.clone());
+            }
+        }
+
+        let item = Self {
             ident,
-            attrs: attrs.into(),
+            attrs,
             fields,
-        }
+            dialects,
+        };
+        crate::dialect::check(ident, &item.dialects, &item.group_requirements())?;
+        Ok(item)
     }
-}
 
-impl<'a> quote::ToTokens for Item<'a> {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let ident = self.ident;
-        let pattern = self.attrs.pattern.value();
-        let regex_const = syn::Ident::new(
-            &format!("{}_REGEX", self.ident.to_string().TO_SHOUTY_SNEK_CASE()),
-            self.ident.span(),
-        );
+    /// The groups `FromCaptures` needs. Fields of a sequence are always
+    /// parsed from their group.
+    fn group_requirements(&self) -> crate::captures::Requirements {
+        let groups = match &self.attrs.pattern {
+            StructPattern::Sequence(_) => (0..self.fields.len())
+                .zip(self.fields.iter())
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => format!("_{}", i),
+                })
+                .collect(),
+            _ => crate::captures::required_prefixed_groups(self.fields, None),
+        };
+        vec![(None, groups)]
+    }
 
-        // This method is not necessary for the trait implementation,
-        // but deduplicates some field-based logic for the others
-        let from_capture_impl = match self.fields {
+    /// Generate `__from_regex_capture`, reading each field from the capture
+    /// group of the same name in `pattern`
+    fn impl_from_capture(&self, pattern: &str) -> proc_macro2::TokenStream {
+        match self.fields {
             syn::Fields::Named(syn::FieldsNamed { .. }) => {
-                let pattern = self.attrs.pattern.value();
                 let (field_names, field_statements) = crate::captures::impl_fields_from_capture(
-                    &crate::captures::from_regex_pattern(&pattern),
-                    &self.fields,
+                    &crate::captures::from_regex_pattern(pattern),
+                    self.fields,
                     None,
                 );
 
@@ -78,10 +260,9 @@ impl<'a> quote::ToTokens for Item<'a> {
                 }
             }
             syn::Fields::Unnamed(syn::FieldsUnnamed { .. }) => {
-                let pattern = self.attrs.pattern.value();
                 let (assigned_names, field_statements) = crate::captures::impl_fields_from_capture(
-                    &crate::captures::from_regex_pattern(&pattern),
-                    &self.fields,
+                    &crate::captures::from_regex_pattern(pattern),
+                    self.fields,
                     None,
                 );
 
@@ -99,50 +280,362 @@ impl<'a> quote::ToTokens for Item<'a> {
                     }
                 }
             }
+        }
+    }
+
+    /// Generate the body of `FromCaptures::from_captures_prefixed`, reading
+    /// each field from the capture group of the same name
+    fn impl_from_prefixed_capture(&self) -> proc_macro2::TokenStream {
+        let (names, statements) =
+            crate::captures::impl_fields_from_prefixed_capture(self.fields, None);
+        let constructor = match self.fields {
+            syn::Fields::Named(_) => quote! { Self { #(#names),* } },
+            syn::Fields::Unnamed(_) => quote! { Self( #(#names),* ) },
+            syn::Fields::Unit => quote! { Self },
+        };
+        quote! {
+            #(#statements)*
+            Some(#constructor)
+        }
+    }
+
+    /// Generate an expression building the pattern of a sequence struct (at
+    /// runtime, since it may include other types' patterns), its
+    /// `__from_regex_capture`, and the body of
+    /// `FromCaptures::from_captures_prefixed`.
+    ///
+    /// Each field is matched by its own `pattern` (and parsed with `FromStr`),
+    /// or by its type's patterns (and parsed with its `FromRegex`
+    /// implementation).
+    fn impl_sequence(
+        &self,
+        separator: Option<&syn::LitStr>,
+    ) -> (
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+        proc_macro2::TokenStream,
+    ) {
+        if self.fields.is_empty() {
+            abort!(
+                self.ident.span(),
+                "Structs without fields need a `pattern` (there's nothing to build one from)"
+            );
+        }
+
+        let mut parts = Vec::new();
+        let mut names = Vec::new();
+        let mut statements = Vec::new();
+        let mut prefixed_statements = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            let name = match &field.ident {
+                Some(ident) => ident.clone(),
+                None => syn::Ident::new(&format!("_{}", i), field.span()),
+            };
+            let group = syn::LitStr::new(&name.to_string(), field.span());
+            let prefixed_group = crate::captures::impl_prefixed_group(&group);
+            let ty = &field.ty;
+
+            let mut pattern = None;
+            for meta in crate::Attributes::from(field.attrs.as_ref()) {
+                if let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(lit),
+                    ..
+                })) = meta
+                {
+                    if path.is_ident(FIELD_ATTRIBUTE_PATTERN) {
+                        pattern = Some(lit);
+                    }
+                }
+            }
+
+            match pattern {
+                Some(pattern) => {
+                    let pattern = format!("(?P<{}>{})", name, pattern.value());
+                    parts.push(quote! { String::from(#pattern) });
+                    statements.push(quote! {
+                        let #name = captures.name(#group).unwrap().as_str().parse().ok()?;
+                    });
+                    prefixed_statements.push(quote! {
+                        let #name = #prefixed_group?.as_str().parse().ok()?;
+                    });
+                }
+                None if is_std_type(ty) => abort!(
+                    ty.span(),
+                    "Field `{}` of `{}` needs a `pattern`, since `{}` doesn't implement `FromRegex`",
+                    name,
+                    self.ident,
+                    quote! { #ty }
+                ),
+                None => {
+                    let message = format!(
+                        "The patterns of `{}` (field `{}` of `{}`) are unknown",
+                        quote! { #ty },
+                        name,
+                        self.ident
+                    );
+                    // Spanned, so that a type without `FromRegex` is reported
+                    // at the field
+                    parts.push(quote_spanned! { ty.span() =>
+                        format!(
+                            "(?P<{}>{})",
+                            #group,
+                            from_regex::non_capturing_pattern::<#ty>().expect(#message)
+                        )
+                    });
+                    statements.push(quote_spanned! { ty.span() =>
+                        let #name = <#ty as from_regex::FromRegex>::from_regex(captures.name(#group).unwrap().as_str())?;
+                    });
+                    prefixed_statements.push(quote_spanned! { ty.span() =>
+                        let #name = <#ty as from_regex::FromRegex>::from_regex(#prefixed_group?.as_str())?;
+                    });
+                }
+            }
+            names.push(name);
+        }
+
+        // Wrapped so that an alternation doesn't swallow the fields around it
+        let separator = separator
+            .map(|separator| format!("(?:{})", separator.value()))
+            .unwrap_or_default();
+        let constructor = match self.fields {
+            syn::Fields::Named(_) => quote! { Self { #(#names),* } },
+            _ => quote! { Self( #(#names),* ) },
+        };
+        (
+            quote! { [#(#parts),*].join(#separator) },
+            quote! {
+                fn __from_regex_capture(captures: from_regex::Captures) -> Option<Self> {
+                    #(#statements)*
+                    Some(#constructor)
+                }
+            },
+            quote! {
+                #(#prefixed_statements)*
+                Some(#constructor)
+            },
+        )
+    }
+
+    /// Generate the [`FromRegex`] implementation for a transparent struct,
+    /// which wraps whatever its inner type parses (if it passes validation)
+    fn impl_transparent(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        let transparent = Transparent::new(ident, self.fields);
+        transparent.check_recursion(ident);
+        let inner = transparent.inner_type();
+        let constructor = transparent.constructor(quote! { Self }, quote! { inner });
+
+        // Without validation every inner value is kept, so `from_regex`
+        // doesn't need to look past the inner type's first choice
+        let (valid, impl_from_regex) = match &self.attrs.validate {
+            Some(validate) => (
+                quote! { #validate(value) },
+                quote! {
+                    fn from_regex(s: &str) -> Option<Self> {
+                        Self::from_regex_all(s).into_iter().next()
+                    }
+                },
+            ),
+            None => (
+                quote! { true },
+                quote! {
+                    fn from_regex(s: &str) -> Option<Self> {
+                        <#inner>::from_regex(s).map(Self::__from_regex_inner)
+                    }
+                },
+            ),
+        };
+
+        quote! {
+            impl #ident {
+                fn __from_regex_inner(inner: #inner) -> Self {
+                    #constructor
+                }
+
+                #[allow(unused_variables)]
+                fn __from_regex_valid(value: &Self) -> bool {
+                    #valid
+                }
+            }
+            impl from_regex::FromRegex for #ident {
+                #impl_from_regex
+
+                fn from_regex_all(s: &str) -> Vec<Self> {
+                    <#inner>::from_regex_all(s)
+                        .into_iter()
+                        .map(Self::__from_regex_inner)
+                        .filter(Self::__from_regex_valid)
+                        .collect()
+                }
+
+                fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
+                    // Keep the inner type's choice of matches (rather than
+                    // resolving overlaps again), minus any invalid ones
+                    let mut locations = from_regex::SegmentMap::new();
+                    for (range, inner) in <#inner>::match_locations(s) {
+                        let value = Self::__from_regex_inner(inner);
+                        if Self::__from_regex_valid(&value) {
+                            locations.insert_if_empty(range, value);
+                        }
+                    }
+                    locations
+                }
+
+                fn match_candidates(s: &str) -> Vec<from_regex::Candidate<Self>> {
+                    <#inner>::match_candidates(s)
+                        .into_iter()
+                        .map(|candidate| from_regex::Candidate {
+                            range: candidate.range,
+                            priority: candidate.priority,
+                            value: Self::__from_regex_inner(candidate.value),
+                        })
+                        .filter(|candidate| Self::__from_regex_valid(&candidate.value))
+                        .collect()
+                }
+
+                fn match_all_overlapping(s: &str) -> Vec<(std::ops::Range<usize>, Self)> {
+                    <#inner>::match_all_overlapping(s)
+                        .into_iter()
+                        .map(|(range, inner)| (range, Self::__from_regex_inner(inner)))
+                        .filter(|(_, value)| Self::__from_regex_valid(value))
+                        .collect()
+                }
+
+                fn find_at(s: &str, start: usize) -> Option<from_regex::Candidate<Self>> {
+                    Self::finder(s).find_at(start)
+                }
+
+                fn finder<'s>(s: &'s str) -> from_regex::Finder<'s, Self>
+                where
+                    Self: 's,
+                {
+                    // Invalid values are dropped, and searching resumes after them
+                    let mut inner = <#inner>::finder(s);
+                    from_regex::Finder::new(move |start| {
+                        let mut pos = Some(start);
+                        while let Some(candidate) = pos.and_then(|pos| inner.find_at(pos)) {
+                            let value = Self::__from_regex_inner(candidate.value);
+                            if Self::__from_regex_valid(&value) {
+                                return Some(from_regex::Candidate {
+                                    range: candidate.range,
+                                    priority: candidate.priority,
+                                    value,
+                                });
+                            }
+                            pos = from_regex::resume_at(s, &candidate.range);
+                        }
+                        None
+                    })
+                }
+
+                fn search_patterns() -> Option<Vec<String>> {
+                    <#inner>::search_patterns()
+                }
+
+                fn max_match_len() -> Option<usize> {
+                    <#inner>::max_match_len()
+                }
+            }
+        }
+    }
+}
+
+impl<'a> quote::ToTokens for Item<'a> {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let ident = self.ident;
+        tokens.extend(crate::dialect::impl_dialects(ident, &self.dialects));
+        if let Some(path) = &self.attrs.pattern_file {
+            tokens.extend(crate::pattern_file::impl_tracking(path));
+        }
+        if let Some(write) = crate::display::impl_write(self.attrs.display.as_ref(), None) {
+            let (pattern, _) = crate::display::bind_fields(self.fields);
+            tokens.extend(crate::display::impl_display(
+                ident,
+                vec![quote! { Self #pattern => #write }],
+            ));
+        }
+
+        let (pattern, from_capture_impl, from_captures_impl) = match &self.attrs.pattern {
+            StructPattern::Some(pattern) => {
+                let pattern = pattern.value();
+                let from_capture_impl = self.impl_from_capture(&pattern);
+                (
+                    quote! { #pattern },
+                    from_capture_impl,
+                    self.impl_from_prefixed_capture(),
+                )
+            }
+            StructPattern::Sequence(separator) => self.impl_sequence(separator.as_ref()),
+            StructPattern::Transparent => {
+                tokens.extend(self.impl_transparent());
+                return;
+            }
         };
+        let impl_group_requirements =
+            crate::captures::impl_group_requirements(self.group_requirements());
+        let regex_const = syn::Ident::new(
+            &format!("{}_REGEX", self.ident.to_string().TO_SHOUTY_SNEK_CASE()),
+            self.ident.span(),
+        );
+        let full_regex_const = syn::Ident::new(
+            &format!(
+                "{}_FULL_REGEX",
+                self.ident.to_string().TO_SHOUTY_SNEK_CASE()
+            ),
+            self.ident.span(),
+        );
+        let impl_max_match_len = crate::impl_max_match_len(self.attrs.max_len.as_ref());
 
-        // If unit struct, we don't need to capture (but we'll still find a
-        // Match), since Regex::is_match doesn't ensure the entire string is
-        // matched, just that a match exists in it.
+        // Parsing uses the pattern anchored to the entire input, so that an
+        // earlier alternative matching only part of it doesn't hide a later
+        // one matching all of it. A unit struct doesn't need to capture.
         let impl_from_regex = if matches!(self.fields, syn::Fields::Unit) {
             quote! {
                 fn from_regex(s: &str) -> Option<Self> {
-                    match #regex_const.find(s) {
-                        Some(mat) if (mat.end() - mat.start()) == s.len() => Some(Self),
-                        Some(_) => None,
-                        None => None,
+                    if #full_regex_const.is_match(s) {
+                        Some(Self)
+                    } else {
+                        None
                     }
                 }
             }
         } else {
             quote! {
                 fn from_regex(s: &str) -> Option<Self> {
-                    match #regex_const.captures(s) {
-                        Some(cap) if cap[0].len() == s.len() => Self::__from_regex_capture(cap),
-                        Some(_) => None,
-                        None => None,
-                    }
+                    Self::__from_regex_capture(#full_regex_const.captures(s)?)
                 }
             }
         };
 
         // Similar to above, Unit struct doesn't need captures
-        let impl_match_locations = if matches!(self.fields, syn::Fields::Unit) {
+        let impl_match_candidates = if matches!(self.fields, syn::Fields::Unit) {
             quote! {
-                fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
-                    #regex_const.find_iter(s).map(|mat| (mat.range(), Self)).collect()
+                fn match_candidates(s: &str) -> Vec<from_regex::Candidate<Self>> {
+                    #regex_const
+                        .find_iter(s)
+                        .map(|mat| from_regex::Candidate {
+                            range: mat.range(),
+                            priority: 0,
+                            value: Self,
+                        })
+                        .collect()
                 }
             }
         } else {
             quote! {
-                fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
+                fn match_candidates(s: &str) -> Vec<from_regex::Candidate<Self>> {
                     #regex_const
                         .captures_iter(s)
                         .filter_map(|cap| {
                             // Unwrap is fine for get(0), because otherwise it wouldn't have matched
                             let range = cap.get(0).unwrap().range();
                             Self::__from_regex_capture(cap).map(|value| {
-                                (range, value)
+                                from_regex::Candidate {
+                                    range,
+                                    priority: 0,
+                                    value,
+                                }
                             })
                         })
                         .collect()
@@ -152,15 +645,143 @@ impl<'a> quote::ToTokens for Item<'a> {
 
         tokens.extend(quote! {
             from_regex::lazy_static! {
-                static ref #regex_const: from_regex::Regex = from_regex::Regex::new(#pattern).expect("Failed to compile regex");
+                static ref #regex_const: from_regex::Regex = from_regex::Regex::new(&#pattern).expect("Failed to compile regex");
+                static ref #full_regex_const: from_regex::Regex = from_regex::Regex::new(&format!(r"\A(?:{})\z", #regex_const.as_str())).expect("Failed to compile regex");
             }
             impl #ident {
                 #from_capture_impl
             }
             impl from_regex::FromRegex for #ident {
                 #impl_from_regex
-                #impl_match_locations
+
+                fn match_locations(s: &str) -> from_regex::SegmentMap<usize, Self> {
+                    from_regex::OverlapPolicy::FirstFound.resolve(Self::match_candidates(s))
+                }
+
+                #impl_match_candidates
+
+                fn find_at(s: &str, start: usize) -> Option<from_regex::Candidate<Self>> {
+                    from_regex::captures_from(&#regex_const, s, start).find_map(|cap| {
+                        let range = cap.get(0).unwrap().range();
+                        Self::__from_regex_capture(cap).map(|value| from_regex::Candidate {
+                            range,
+                            priority: 0,
+                            value,
+                        })
+                    })
+                }
+
+                fn finder<'s>(s: &'s str) -> from_regex::Finder<'s, Self>
+                where
+                    Self: 's,
+                {
+                    from_regex::Finder::new(move |start| Self::find_at(s, start))
+                }
+
+                fn match_all_overlapping(s: &str) -> Vec<(std::ops::Range<usize>, Self)> {
+                    from_regex::captures_overlapping(&#regex_const, s)
+                        .filter_map(|cap| {
+                            let range = cap.get(0).unwrap().range();
+                            Self::__from_regex_capture(cap).map(|value| (range, value))
+                        })
+                        .collect()
+                }
+
+                fn search_patterns() -> Option<Vec<String>> {
+                    Some(vec![String::from(#regex_const.as_str())])
+                }
+
+                #impl_max_match_len
+            }
+            impl from_regex::FromCaptures for #ident {
+                #[allow(unused_variables)]
+                fn from_captures_prefixed(captures: &from_regex::Captures, prefix: &str) -> Option<Self> {
+                    #from_captures_impl
+                }
+
+                #impl_group_requirements
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_std_type, Item};
+
+    /// The error deriving `item` gives, if any
+    fn error(item: &str) -> Option<String> {
+        let input = syn::parse_str::<syn::DeriveInput>(item).unwrap();
+        let fields = match &input.data {
+            syn::Data::Struct(data) => &data.fields,
+            _ => unreachable!(),
+        };
+        Item::try_new(&input.ident, &input.attrs, fields)
+            .err()
+            .map(|err| err.to_string())
+    }
+
+    #[test]
+    fn dialect_errors() {
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<x>x)")]
+                #[from_regex(dialect = "a", pattern = "(?P<x>y)")]
+                struct S { x: String }"#
+            )
+            .as_deref(),
+            Some("Dialect `a` is declared twice")
+        );
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<x>x)")]
+                #[from_regex(default_dialect = "b")]
+                struct S { x: String }"#
+            )
+            .as_deref(),
+            Some("No dialect named `b`")
+        );
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<x>x)")]
+                #[from_regex(pattern = "(?P<x>y)", default_dialect = "a")]
+                struct S { x: String }"#
+            )
+            .as_deref(),
+            Some("Only structs without a pattern of their own can have a default dialect")
+        );
+
+        // Dialects' groups are checked as the runtime parser would
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<x>x)?")]
+                struct S { x: String }"#
+            )
+            .as_deref(),
+            Some("Dialect `a` can't construct `S`: The group `x` might not participate in a match")
+        );
+        assert_eq!(
+            error(
+                r#"#[from_regex(dialect = "a", pattern = "(?P<x>x)?")]
+                struct S { x: Option<String> }"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn std_types() {
+        for ty in [
+            "String",
+            "u32",
+            "std::string::String",
+            "Option<u8>",
+            "&'static str",
+        ] {
+            assert!(is_std_type(&syn::parse_str(ty).unwrap()), "{}", ty);
+        }
+        for ty in ["SortedEnum", "crate::Address", "Box<Command>"] {
+            assert!(!is_std_type(&syn::parse_str(ty).unwrap()), "{}", ty);
+        }
+    }
+}
@@ -3,9 +3,14 @@ use quote::quote;
 use quote::ToTokens;
 use syn::spanned::Spanned;
 
+mod ambiguity;
 mod captures;
+mod dialect;
+mod display;
 mod impl_enum;
 mod impl_struct;
+mod pattern_file;
+mod transparent;
 
 // TODO: for unit structs/variants, don't require a named capture to
 // capture an entire string?
@@ -17,19 +22,96 @@ mod impl_struct;
 /// ## Implementation Notes
 ///
 /// - Default implementations of `from_regex` will only match if the *entire string* is matched
+/// - `FromCaptures` is also implemented (except for transparent structs), to
+///   construct values from the captures of other regexes
 ///
 /// ## Usage with Structs
 ///
 /// ### Item Level Attributes
 ///
-///
+/// - `pattern = "regex"`: The pattern to match, with a named capture group
+///   for each field
+/// - `pattern_file = "path"`: Read the pattern from a file instead (relative
+///   to the crate's `Cargo.toml`), rebuilding when it changes. The pattern is
+///   compiled in verbose mode (`(?x)`), so whitespace is ignored and `#`
+///   starts a comment.
+/// - `transparent`: Parse (and search for) the struct with the `FromRegex`
+///   implementation of its field's type instead, in the same way as
+///   transparent enum variants (see below)
+/// - `validate = "path::to::fn"`: Only keep values of a transparent struct
+///   for which the given `fn(&Self) -> bool` returns `true`
+/// - `separator = "regex"`: Without a `pattern`, the struct's pattern is
+///   assembled from its fields in order, joined by this separator (if any).
+///   Each field is matched by its own `#[from_regex(pattern = "regex")]` and
+///   parsed with `FromStr`, or else matched and parsed by its type's
+///   `FromRegex` implementation.
+/// - `max_len = N`: An upper bound on the length of a match in bytes
+///   (including any context the pattern looks at, such as `\b`), so that
+///   `update_locations` only needs to search near an edit. Transparent
+///   structs use their field type's bound.
+/// - `display = "format"`: Also implement `Display`, with a format string
+///   naming the struct's fields (e.g. `"{host}:{port}"`, or `"{_0}"` for
+///   tuple structs). This should produce text the pattern matches, so that
+///   values round-trip (see `from_regex::assert_round_trip`).
+/// - `pattern = "regex", dialect = "name"` (in one attribute, which can be
+///   repeated): A named pattern to parse with at runtime, instead of the
+///   struct's own (see `from_regex::Dialects`). Its groups are checked at
+///   compile time, as `from_regex::Parser::new` would check them.
+/// - `default_dialect = "name"`: The dialect to use as the struct's pattern,
+///   if it doesn't have one of its own. Defaults to the first dialect.
 ///
 /// ## Usage with Enums
 ///
 /// ### Item Level Attributes
 ///
 /// - Match Mode: TODO
+/// - `ambiguity = "allow" | "warn" | "deny"`: What to do if two patterned
+///   variants with the same priority can both match some input (checked at
+///   compile time, with an example input reported). Defaults to `"warn"`
+///   for `match_mode = "longest"`, and `"allow"` for `match_mode = "first"`
+///   (where declaration order is expected to resolve these).
+/// - `lexer`: Also implement `Lexer`, splitting input into a contiguous
+///   sequence of tokens (the longest at each position). Needs
+///   `match_mode = "longest"`, and can't have transparent variants.
+/// - `max_len = N`: As for structs (above).
+/// - `pattern = "regex", dialect = "name"`: As for structs (above), with a
+///   group named after each variant that can be matched, and its fields'
+///   groups named as for `match_mode = "longest"` (e.g. `Variant_field`).
+///   The variants' own patterns are always used by default (so enums can't
+///   have a `default_dialect`).
+///
+/// ### Variant Level Attributes
 ///
+/// - `pattern = "regex"`, `pattern_file = "path"`: The pattern to match, as
+///   for structs (above).
+/// - `priority = N`: Variants are tried (and searched for) in order of
+///   priority, highest first. Equal priorities (the default is `0`) keep
+///   declaration order. This decides which variant is returned when several
+///   match the entire input, and which of two equally long overlapping
+///   matches is kept while searching.
+/// - `transparent`: Parse (and search for) the variant with the `FromRegex`
+///   implementation of its field's type. Variants with several fields must
+///   mark the one to parse with `#[from_regex(transparent)]`, and the others
+///   are filled with `Default::default()` (or `#[from_regex(default = "expr")]`).
+///   `Box`, `Arc` and `Rc` fields are parsed as the type they wrap. The
+///   field can't be the enum itself, since it would parse the same input
+///   forever.
+/// - `default`: Return this variant from `from_regex` when nothing else
+///   matches. The field marked `#[from_regex(input)]` (or the only field of
+///   a tuple variant, if it's a `String`) receives the unmatched input, and
+///   the others are filled with `Default::default()`.
+/// - `default = "expr"`: As above, but construct the variant by calling the
+///   given expression (e.g. a `fn(&str) -> Self` path, or a closure) with the
+///   unmatched input.
+/// - `skip` (lexers only): Consume tokens of this variant (e.g. whitespace or
+///   comments) without returning them.
+/// - `state = "name"` (lexers only): Only lex this variant in the named
+///   state, rather than the initial one.
+/// - `push = "name"`, `pop` (lexers only): Enter the named state, or return
+///   to the previous one, after lexing this variant.
+/// - `display = "format"`: Also implement `Display`, with a format string
+///   per variant as for structs (above). If any variant has one, all need
+///   one, except transparent variants, which display their parsed field.
 ///
 #[proc_macro_error]
 #[proc_macro_derive(FromRegex, attributes(from_regex))]
@@ -61,6 +143,18 @@ pub fn derive_str(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
 const ATTRIBUTE: &str = "from_regex";
 
+/// Generate `max_match_len` from the `max_len` attribute (if set)
+fn impl_max_match_len(max_len: Option<&syn::LitInt>) -> proc_macro2::TokenStream {
+    match max_len {
+        Some(max_len) => quote! {
+            fn max_match_len() -> Option<usize> {
+                Some(#max_len)
+            }
+        },
+        None => quote! {},
+    }
+}
+
 enum Item<'a> {
     Enum(impl_enum::Item<'a>),
     Struct(impl_struct::Item<'a>),
@@ -74,11 +168,11 @@ impl<'a> From<&'a syn::DeriveInput> for Item<'a> {
         match data {
             syn::Data::Enum(data_enum) => Item::Enum(impl_enum::Item::new(
                 ident,
-                &attrs,
+                attrs,
                 data_enum.variants.iter(),
             )),
             syn::Data::Struct(syn::DataStruct { fields, .. }) => {
-                Item::Struct(impl_struct::Item::new(ident, &attrs, &fields))
+                Item::Struct(impl_struct::Item::new(ident, attrs, fields))
             }
             syn::Data::Union(syn::DataUnion { union_token, .. }) => {
                 abort!(union_token.span(), "Unsupported item type")
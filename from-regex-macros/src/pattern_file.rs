@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use quote::quote;
+
+/// Read the pattern in the file at `path` (relative to the manifest of the
+/// crate being compiled), returning it (in verbose mode, so that it can
+/// have comments and line breaks) along with the file's full path
+pub fn read(path: &syn::LitStr) -> syn::Result<(syn::LitStr, String)> {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+        syn::Error::new(
+            path.span(),
+            "Pattern files are found relative to `CARGO_MANIFEST_DIR`, which isn't set (build with Cargo)",
+        )
+    })?;
+    let full_path = PathBuf::from(dir).join(path.value());
+    let contents = std::fs::read_to_string(&full_path).map_err(|err| {
+        syn::Error::new(
+            path.span(),
+            format!(
+                "Couldn't read pattern file `{}`: {}",
+                full_path.display(),
+                err
+            ),
+        )
+    })?;
+
+    // Scope the flag to the file's pattern (since it may be embedded in
+    // others), and end any comment on its last line
+    let pattern = format!("(?x:{}\n)", contents);
+    if let Err(err) = regex::Regex::new(&pattern) {
+        return Err(syn::Error::new(
+            path.span(),
+            format!("Invalid pattern in `{}`: {}", full_path.display(), err),
+        ));
+    }
+    Ok((
+        syn::LitStr::new(&pattern, path.span()),
+        full_path.to_string_lossy().into_owned(),
+    ))
+}
+
+/// Generate a use of the pattern file at `path`, so that the item is
+/// rebuilt when it changes
+pub fn impl_tracking(path: &str) -> proc_macro2::TokenStream {
+    quote! {
+        const _: &[u8] = include_bytes!(#path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+
+    fn read_error(path: &str) -> String {
+        let path = syn::LitStr::new(path, proc_macro2::Span::call_site());
+        read(&path).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn errors() {
+        let error = read_error("missing.re");
+        assert!(
+            error.starts_with("Couldn't read pattern file `"),
+            "{}",
+            error
+        );
+        assert!(error.contains("missing.re"), "{}", error);
+
+        // Absolute paths are used as they are
+        let path = std::env::temp_dir().join("from_regex_invalid_pattern.re");
+        std::fs::write(&path, "(?P<unclosed> a").unwrap();
+        let error = read_error(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.starts_with("Invalid pattern in `"), "{}", error);
+        assert!(error.contains("unclosed group"), "{}", error);
+    }
+}
@@ -0,0 +1,192 @@
+use proc_macro_error::abort;
+use quote::quote;
+use syn::spanned::Spanned;
+
+const FIELD_ATTRIBUTE_TRANSPARENT: &str = "transparent";
+const FIELD_ATTRIBUTE_DEFAULT: &str = "default";
+
+/// Smart pointers an inner value can be wrapped in (after it's parsed)
+const WRAPPERS: &[&str] = &["Box", "Arc", "Rc"];
+
+/// Fields of a transparent item, one of which holds the inner [`FromRegex`]
+/// value. The rest are filled with their `default` expression (if given) or
+/// `Default::default()`.
+pub struct Transparent<'a> {
+    fields: &'a syn::Fields,
+    inner: usize,
+    inner_type: &'a syn::Type,
+    wrapped: bool,
+    defaults: Vec<Option<syn::Expr>>,
+}
+
+impl<'a> Transparent<'a> {
+    /// Find the inner field. This is the only field, or the one marked with
+    /// `#[from_regex(transparent)]` if there are several.
+    pub fn new(ident: &syn::Ident, fields: &'a syn::Fields) -> Self {
+        let mut inner = None;
+        let mut defaults = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let mut default = None;
+            for meta in crate::Attributes::from(field.attrs.as_ref()) {
+                match meta {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path))
+                        if path.is_ident(FIELD_ATTRIBUTE_TRANSPARENT) =>
+                    {
+                        if inner.is_some() {
+                            abort!(path.span(), "Only one field can be transparent");
+                        }
+                        inner = Some(i);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    })) if path.is_ident(FIELD_ATTRIBUTE_DEFAULT) => match lit.parse() {
+                        Ok(expr) => default = Some(expr),
+                        Err(err) => abort!(lit.span(), "Invalid default expression: {}", err),
+                    },
+                    _ => {}
+                }
+            }
+            defaults.push(default);
+        }
+
+        let inner = match (inner, fields.len()) {
+            (Some(inner), _) => inner,
+            (None, 1) => 0,
+            (None, 0) => abort!(
+                ident.span(),
+                "The `transparent` attribute requires a field to hold the inner value"
+            ),
+            (None, _) => abort!(
+                ident.span(),
+                "Mark the field holding the inner value with `#[from_regex(transparent)]`"
+            ),
+        };
+        if defaults[inner].is_some() {
+            abort!(ident.span(), "The transparent field can't have a default");
+        }
+
+        let field_type = &fields.iter().nth(inner).unwrap().ty;
+        let (inner_type, wrapped) = match unwrap_pointer(field_type) {
+            Some(inner_type) => (inner_type, true),
+            None => (field_type, false),
+        };
+
+        Self {
+            fields,
+            inner,
+            inner_type,
+            wrapped,
+            defaults,
+        }
+    }
+
+    /// The position of the inner field
+    pub fn inner_index(&self) -> usize {
+        self.inner
+    }
+
+    /// The type parsed to construct the inner field
+    pub fn inner_type(&self) -> &syn::Type {
+        self.inner_type
+    }
+
+    /// Whether the inner type is `item` itself (possibly behind a pointer),
+    /// which would parse the same input again without ever consuming any
+    pub fn is_recursive(&self, item: &syn::Ident) -> bool {
+        match self.inner_type {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => matches!(
+                path.segments.last(),
+                Some(segment) if segment.ident == *item || segment.ident == "Self"
+            ),
+            _ => false,
+        }
+    }
+
+    /// Abort if the inner type is `item` itself (see [`is_recursive`])
+    ///
+    /// [`is_recursive`]: Self::is_recursive
+    pub fn check_recursion(&self, item: &syn::Ident) {
+        if self.is_recursive(item) {
+            abort!(
+                self.inner_type.span(),
+                "`{}` can't be transparent over itself, since parsing it would recurse forever on the same input",
+                item
+            );
+        }
+    }
+
+    /// Generate an expression constructing `path` (e.g. `Self::Variant`) from
+    /// the inner value `value`
+    pub fn constructor(
+        &self,
+        path: proc_macro2::TokenStream,
+        value: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let values = self.fields.iter().enumerate().map(|(i, field)| {
+            let ty = &field.ty;
+            if i != self.inner {
+                match &self.defaults[i] {
+                    Some(expr) => quote! { #expr },
+                    None => quote! { <#ty>::default() },
+                }
+            } else if self.wrapped {
+                quote! { <#ty>::new(#value) }
+            } else {
+                value.clone()
+            }
+        });
+
+        match self.fields {
+            syn::Fields::Named(syn::FieldsNamed { named, .. }) => {
+                let names = named.iter().map(|field| &field.ident);
+                quote! { #path { #( #names: #values ),* } }
+            }
+            syn::Fields::Unnamed(_) => quote! { #path ( #( #values ),* ) },
+            syn::Fields::Unit => unreachable!("Unit items have no inner field"),
+        }
+    }
+}
+
+/// If `ty` is one of [`WRAPPERS`], get the type it wraps
+fn unwrap_pointer(ty: &syn::Type) -> Option<&syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path.segments.last()?,
+        _ => return None,
+    };
+    if !WRAPPERS.iter().any(|wrapper| segment.ident == wrapper) {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
+            args, ..
+        }) if args.len() == 1 => match args.first() {
+            Some(syn::GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transparent;
+
+    fn is_recursive(item: &str) -> bool {
+        let item = syn::parse_str::<syn::ItemStruct>(item).unwrap();
+        Transparent::new(&item.ident, &item.fields).is_recursive(&item.ident)
+    }
+
+    #[test]
+    fn recursion() {
+        assert!(is_recursive("struct Expr(Box<Expr>);"));
+        assert!(is_recursive("struct Expr(std::rc::Rc<Self>);"));
+        assert!(is_recursive(
+            "struct Expr { #[from_regex(transparent)] inner: crate::Expr, name: String }"
+        ));
+        assert!(!is_recursive("struct Expr(Box<Term>);"));
+        assert!(!is_recursive("struct Expr(Vec<Expr>);"));
+    }
+}
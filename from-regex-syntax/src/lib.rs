@@ -0,0 +1,255 @@
+//! Pattern analysis shared by the `from-regex` crate (checking patterns
+//! given at runtime) and its derive macros (checking patterns at compile
+//! time), so that both accept the same patterns.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use regex_syntax::ast::{self, Ast, Flags, GroupKind};
+use regex_syntax::hir::{Hir, HirKind};
+
+/// For each named group of a pattern, the groups it participates in every
+/// match of (with `None` standing for the whole pattern)
+pub type Participation = HashMap<String, Vec<Option<String>>>;
+
+/// Find the groups each named group of a parsed pattern participates in
+/// every match of
+pub fn participation(hir: &Hir) -> Participation {
+    fn walk(hir: &Hir, within: Vec<Option<String>>, groups: &mut Participation) {
+        match hir.kind() {
+            HirKind::Capture(capture) => {
+                let mut inner = within.clone();
+                if let Some(name) = &capture.name {
+                    inner.push(Some(name.to_string()));
+                    groups.insert(name.to_string(), within);
+                }
+                walk(&capture.sub, inner, groups);
+            }
+            HirKind::Concat(subs) => {
+                for sub in subs {
+                    walk(sub, within.clone(), groups);
+                }
+            }
+            // Anything that can be skipped isn't guaranteed by the groups
+            // around it
+            HirKind::Alternation(subs) => {
+                for sub in subs {
+                    walk(sub, Vec::new(), groups);
+                }
+            }
+            HirKind::Repetition(repetition) => {
+                let within = if repetition.min > 0 {
+                    within
+                } else {
+                    Vec::new()
+                };
+                walk(&repetition.sub, within, groups);
+            }
+            _ => {}
+        }
+    }
+
+    let mut groups = HashMap::new();
+    walk(hir, vec![None], &mut groups);
+    groups
+}
+
+/// Why a pattern doesn't have the groups a type needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequirementError<S> {
+    /// A required group isn't in the pattern
+    MissingGroup(S),
+
+    /// A required group might not participate in a match
+    OptionalGroup(S),
+
+    /// None of the groups the requirements are within are in the pattern
+    NoVariants,
+}
+
+impl<S: fmt::Display> fmt::Display for RequirementError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequirementError::MissingGroup(group) => {
+                write!(f, "The pattern has no group named `{}`", group)
+            }
+            RequirementError::OptionalGroup(group) => {
+                write!(f, "The group `{}` might not participate in a match", group)
+            }
+            RequirementError::NoVariants => write!(f, "The pattern has no group for any variant"),
+        }
+    }
+}
+
+/// Check a pattern's groups against pairs of the group a requirement is
+/// within (if any) and the groups that must participate in every match of
+/// it. Requirements within a group the pattern doesn't have are skipped,
+/// but at least one must apply (unless there are none).
+pub fn check_requirements<S, G, R>(
+    requirements: R,
+    participation: &Participation,
+) -> Result<(), RequirementError<S>>
+where
+    S: AsRef<str>,
+    G: IntoIterator<Item = S>,
+    R: IntoIterator<Item = (Option<S>, G)>,
+{
+    let mut requirements = requirements.into_iter().peekable();
+    let mut matchable = requirements.peek().is_none();
+    for (within, groups) in requirements {
+        let within = within.as_ref().map(|within| within.as_ref());
+        if let Some(within) = within {
+            if !participation.contains_key(within) {
+                continue;
+            }
+        }
+        matchable = true;
+
+        for group in groups {
+            match participation.get(group.as_ref()) {
+                None => return Err(RequirementError::MissingGroup(group)),
+                Some(groups)
+                    if !groups
+                        .iter()
+                        .any(|participates| participates.as_deref() == within) =>
+                {
+                    return Err(RequirementError::OptionalGroup(group))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if matchable {
+        Ok(())
+    } else {
+        Err(RequirementError::NoVariants)
+    }
+}
+
+/// Rename each named group of a pattern to the name `rename` returns for
+/// it, or make it non-capturing if that's `None`. Only the groups of the
+/// parsed pattern change (with either `(?P<name>...)` or `(?<name>...)`
+/// syntax), rather than any text that looks like one.
+pub fn map_group_names<F>(pattern: &str, mut rename: F) -> Result<String, Box<ast::Error>>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    fn walk<F: FnMut(&str) -> Option<String>>(ast: &mut Ast, rename: &mut F) {
+        match ast {
+            Ast::Group(group) => {
+                if let GroupKind::CaptureName { name, .. } = &mut group.kind {
+                    match rename(&name.name) {
+                        Some(new_name) => name.name = new_name,
+                        None => {
+                            group.kind = GroupKind::NonCapturing(Flags {
+                                span: group.span,
+                                items: Vec::new(),
+                            })
+                        }
+                    }
+                }
+                walk(&mut group.ast, rename);
+            }
+            Ast::Repetition(repetition) => walk(&mut repetition.ast, rename),
+            Ast::Alternation(alternation) => {
+                for ast in &mut alternation.asts {
+                    walk(ast, rename);
+                }
+            }
+            Ast::Concat(concat) => {
+                for ast in &mut concat.asts {
+                    walk(ast, rename);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut ast = ast::parse::Parser::new().parse(pattern).map_err(Box::new)?;
+    walk(&mut ast, &mut rename);
+    let mut mapped = String::new();
+    ast::print::Printer::new()
+        .print(&ast, &mut mapped)
+        .expect("Failed to print pattern");
+    Ok(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_requirements, map_group_names, participation, RequirementError};
+
+    fn check(pattern: &str, requirements: &[(Option<&str>, &[&str])]) -> Result<(), String> {
+        let participation = participation(&regex_syntax::parse(pattern).unwrap());
+        check_requirements(
+            requirements
+                .iter()
+                .map(|(within, groups)| (*within, groups.iter().copied())),
+            &participation,
+        )
+        .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn groups() {
+        let hir = regex_syntax::parse(r"(?P<a>a)(?P<b>b)?(?:(?P<c>c)|d)(?P<e>(?P<f>f)+)").unwrap();
+        let mut groups = participation(&hir).into_iter().collect::<Vec<_>>();
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![
+                (String::from("a"), vec![None]),
+                (String::from("b"), vec![]),
+                (String::from("c"), vec![]),
+                (String::from("e"), vec![None]),
+                (String::from("f"), vec![None, Some(String::from("e"))]),
+            ]
+        );
+    }
+
+    #[test]
+    fn requirements() {
+        let requirements: &[(Option<&str>, &[&str])] = &[(None, &["host", "port"])];
+        assert_eq!(check(r"(?P<host>\w+):(?P<port>\d+)", requirements), Ok(()));
+        assert_eq!(
+            check(r"(?P<host>\w+)", requirements),
+            Err(String::from("The pattern has no group named `port`"))
+        );
+        assert_eq!(
+            check(r"(?P<host>\w+)(:(?P<port>\d+))?", requirements),
+            Err(String::from(
+                "The group `port` might not participate in a match"
+            ))
+        );
+
+        // Variants' requirements apply within their group
+        let requirements: &[(Option<&str>, &[&str])] = &[(Some("A"), &["A_x"]), (Some("B"), &[])];
+        assert_eq!(check(r"(?P<A>(?P<A_x>x))|(?P<B>y)", requirements), Ok(()));
+        assert_eq!(
+            check(r"z", requirements),
+            Err(String::from("The pattern has no group for any variant"))
+        );
+        assert_eq!(
+            check_requirements(Vec::<(Option<&str>, Vec<&str>)>::new(), &Default::default()),
+            Ok(())
+        );
+        assert_eq!(
+            check_requirements(vec![(None, vec!["x"])], &Default::default()),
+            Err(RequirementError::MissingGroup("x"))
+        );
+    }
+
+    #[test]
+    fn group_names() {
+        let pattern = r"(?P<a>x)(?<b>y)\(?P<c>\)[(?<d>]";
+        assert_eq!(
+            map_group_names(pattern, |group| Some(format!("V_{}", group))).unwrap(),
+            r"(?P<V_a>x)(?<V_b>y)\(?P<c>\)[(?<d>]"
+        );
+        assert_eq!(
+            map_group_names(pattern, |_| None).unwrap(),
+            r"(?:x)(?:y)\(?P<c>\)[(?<d>]"
+        );
+        assert!(map_group_names("(?P<a>x", |_| None).is_err());
+    }
+}
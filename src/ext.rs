@@ -0,0 +1,111 @@
+use std::marker::PhantomData;
+
+use crate::{FromRegex, SegmentMap};
+
+/// [`FromRegex`] methods callable on strings, similar to [`str::parse`] for
+/// [`FromStr`](std::str::FromStr) types
+pub trait RegexStrExt {
+    /// Try to construct an instance of `T` from this string (see
+    /// [`FromRegex::from_regex`])
+    fn parse_regex<T: FromRegex>(&self) -> Option<T>;
+
+    /// Search through this string and return all instances of `T` matched
+    /// (see [`FromRegex::matches`])
+    fn find_all<T: FromRegex>(&self) -> Vec<T>;
+
+    /// Search through this string and return all instances of `T` matched,
+    /// as well as the ranges at which they occur (see
+    /// [`FromRegex::match_locations`])
+    fn find_all_locations<T: FromRegex>(&self) -> SegmentMap<usize, T>;
+}
+
+impl RegexStrExt for str {
+    fn parse_regex<T: FromRegex>(&self) -> Option<T> {
+        T::from_regex(self)
+    }
+
+    fn find_all<T: FromRegex>(&self) -> Vec<T> {
+        T::matches(self)
+    }
+
+    fn find_all_locations<T: FromRegex>(&self) -> SegmentMap<usize, T> {
+        T::match_locations(self)
+    }
+}
+
+/// Adapters for parsing each item of an iterator of strings
+pub trait RegexIterExt: Iterator {
+    /// Try to construct an instance of `T` from each item. Items that don't
+    /// match are returned as the `Err` value.
+    fn parse_regex<T: FromRegex>(self) -> ParseRegex<Self, T>
+    where
+        Self: Sized,
+        Self::Item: AsRef<str>,
+    {
+        ParseRegex {
+            iter: self,
+            _type: PhantomData,
+        }
+    }
+
+    /// Try to construct an instance of `T` from each item, skipping any
+    /// that don't match
+    fn filter_parse<T: FromRegex>(self) -> FilterParse<Self, T>
+    where
+        Self: Sized,
+        Self::Item: AsRef<str>,
+    {
+        FilterParse {
+            iter: self,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<I: Iterator> RegexIterExt for I {}
+
+/// Iterator returned by [`RegexIterExt::parse_regex`]
+#[derive(Debug, Clone)]
+pub struct ParseRegex<I, T> {
+    iter: I,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<I, T> Iterator for ParseRegex<I, T>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+    T: FromRegex,
+{
+    type Item = Result<T, I::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|s| T::from_regex(s.as_ref()).ok_or(s))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Iterator returned by [`RegexIterExt::filter_parse`]
+#[derive(Debug, Clone)]
+pub struct FilterParse<I, T> {
+    iter: I,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<I, T> Iterator for FilterParse<I, T>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+    T: FromRegex,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.by_ref().find_map(|s| T::from_regex(s.as_ref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
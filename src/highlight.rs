@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::{to_range, FromRegex, SegmentMap, Token};
+
+/// Colors (ANSI SGR codes) given to names without one set, chosen by the
+/// name's hash so that each name keeps its color between runs
+const PALETTE: &[&str] = &[
+    "31", "32", "33", "34", "35", "36", "91", "92", "93", "94", "95", "96",
+];
+
+/// How a [`Highlighter`] marks up matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Color matches with ANSI escape codes, for terminals
+    Ansi,
+
+    /// Wrap matches in `<span class="...">`, and escape the text
+    Html,
+}
+
+/// Render text with its matches highlighted, each by the name of the enum
+/// variant (or struct) matched (see [`FromRegex::variant_name`])
+///
+/// ```ignore
+/// let html = Highlighter::html().render(text, &Kind::match_locations(text));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    format: Format,
+    class_prefix: String,
+    colors: HashMap<String, String>,
+}
+
+impl Highlighter {
+    /// Highlight in the given format
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            class_prefix: String::new(),
+            colors: HashMap::new(),
+        }
+    }
+
+    /// Highlight with ANSI escape codes
+    pub fn ansi() -> Self {
+        Self::new(Format::Ansi)
+    }
+
+    /// Highlight with HTML `<span>`s
+    pub fn html() -> Self {
+        Self::new(Format::Html)
+    }
+
+    /// Prefix every HTML class name (e.g. `"token-"`)
+    pub fn class_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.class_prefix = prefix.into();
+        self
+    }
+
+    /// Color matches with the given name using an ANSI SGR code (e.g.
+    /// `"1;34"` for bold blue), rather than one from the default palette
+    pub fn color(mut self, name: impl Into<String>, code: impl Into<String>) -> Self {
+        self.colors.insert(name.into(), code.into());
+        self
+    }
+
+    /// Render `text` with the matches found by
+    /// [`FromRegex::match_locations`] highlighted
+    pub fn render<T: FromRegex>(&self, text: &str, locations: &SegmentMap<usize, T>) -> String {
+        let matches = locations
+            .iter()
+            .filter_map(|(range, value)| Some((to_range(range)?, value.variant_name())));
+        self.render_ranges(text, matches)
+    }
+
+    /// Render the result of [`FromRegex::tokenize`] on `text`, with its
+    /// matches highlighted
+    pub fn render_tokens<'s, T, I>(&self, text: &str, tokens: I) -> String
+    where
+        T: FromRegex + 's,
+        I: IntoIterator<Item = Token<'s, T>>,
+    {
+        let mut out = String::with_capacity(text.len());
+        for token in tokens {
+            match token {
+                Token::Match(range, value) => {
+                    self.write_match(&mut out, &text[range], value.variant_name())
+                }
+                Token::Gap(_, gap) => self.write_text(&mut out, gap),
+            }
+        }
+        out
+    }
+
+    /// Render `text` with the given (ordered, non-overlapping) ranges
+    /// highlighted with the given names
+    pub fn render_ranges<'n, I>(&self, text: &str, matches: I) -> String
+    where
+        I: IntoIterator<Item = (Range<usize>, &'n str)>,
+    {
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        for (range, name) in matches {
+            self.write_text(&mut out, &text[pos..range.start]);
+            self.write_match(&mut out, &text[range.clone()], name);
+            pos = range.end;
+        }
+        self.write_text(&mut out, &text[pos..]);
+        out
+    }
+
+    fn write_match(&self, out: &mut String, text: &str, name: &str) {
+        match self.format {
+            Format::Ansi => {
+                let color = match self.colors.get(name) {
+                    Some(color) => color.as_str(),
+                    None => PALETTE[fnv1a(name) % PALETTE.len()],
+                };
+                let _ = write!(out, "\x1b[{}m{}\x1b[0m", color, text);
+            }
+            Format::Html => {
+                out.push_str("<span class=\"");
+                escape_html(out, &self.class_prefix);
+                escape_html(out, name);
+                out.push_str("\">");
+                escape_html(out, text);
+                out.push_str("</span>");
+            }
+        }
+    }
+
+    fn write_text(&self, out: &mut String, text: &str) {
+        match self.format {
+            Format::Ansi => out.push_str(text),
+            Format::Html => escape_html(out, text),
+        }
+    }
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// A hash that's stable between runs (unlike the standard library's)
+fn fnv1a(s: &str) -> usize {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in s.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash as usize
+}
@@ -0,0 +1,112 @@
+use std::ops::Range;
+
+use crate::{to_range, FromRegex, SegmentMap};
+
+/// A change to searched text: the byte range `range` of the old text was
+/// replaced with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit<'a> {
+    /// Byte range of the old text that was replaced
+    pub range: Range<usize>,
+
+    /// The text it was replaced with
+    pub text: &'a str,
+}
+
+impl<'a> Edit<'a> {
+    /// Replace `range` of the old text with `text`
+    pub fn new(range: Range<usize>, text: &'a str) -> Self {
+        Self { range, text }
+    }
+}
+
+/// See [`FromRegex::update_locations`]
+pub(crate) fn update<T>(old: SegmentMap<usize, T>, edit: &Edit, s: &str) -> SegmentMap<usize, T>
+where
+    T: FromRegex + Clone + Eq,
+{
+    let window = match T::max_match_len() {
+        Some(window) => window.max(1),
+        None => return T::match_locations(s),
+    };
+
+    // Old matches untouched by the edit, moved to where they are in the new
+    // text. Those touching it are always rescanned.
+    let edit_end = edit.range.start + edit.text.len();
+    let kept = old
+        .into_iter()
+        .filter_map(|(range, value)| {
+            let range = to_range(&range)?;
+            if range.end <= edit.range.start {
+                Some((range, value))
+            } else if range.start >= edit.range.end {
+                let start = range.start - edit.range.end + edit_end;
+                Some((start..start + range.len(), value))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Start far enough before the edit that no match beginning earlier can
+    // reach it, without splitting a kept match
+    let mut start = edit.range.start.saturating_sub(window);
+    if let Some((range, _)) = kept.iter().find(|(range, _)| range.contains(&start)) {
+        start = range.start;
+    }
+    start = floor_char_boundary(s, start);
+
+    // Extend the rescan past the edit until the matches it finds agree with
+    // the old ones (or the end of the text is reached). The whole text is
+    // searched (for matches starting in the window), so that e.g. `\b` sees
+    // the text around the window.
+    let mut end = edit_end + window;
+    let scanned = loop {
+        end = end.min(s.len());
+        if let Some((range, _)) = kept.iter().find(|(range, _)| range.contains(&end)) {
+            end = range.end;
+        }
+        end = ceil_char_boundary(s, end);
+
+        let scanned = T::match_locations_in(s, start..end)
+            .into_iter()
+            .filter_map(|(range, value)| Some((to_range(&range)?, value)))
+            .collect::<Vec<_>>();
+
+        let tail = end.saturating_sub(window).max(edit_end);
+        let in_tail = |(range, _): &&(Range<usize>, T)| (tail..end).contains(&range.start);
+        if end == s.len()
+            || scanned
+                .iter()
+                .filter(in_tail)
+                .eq(kept.iter().filter(in_tail))
+        {
+            break scanned;
+        }
+        end += window;
+    };
+
+    // Scanned matches start before `end`, but may reach past it
+    let scanned_end = scanned.last().map_or(end, |(range, _)| range.end.max(end));
+    let mut locations = SegmentMap::new();
+    let before = kept.iter().filter(|(range, _)| range.end <= start);
+    let after = kept.iter().filter(|(range, _)| range.start >= scanned_end);
+    for (range, value) in before.chain(scanned.iter()).chain(after) {
+        locations.insert_if_empty(range.clone(), value.clone());
+    }
+    locations
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::ops::Range;
+
+use regex_automata::meta;
+use regex_automata::{Anchored, Input, MatchKind, PatternSet};
+
+use crate::FromRegex;
+
+/// Split input into a contiguous sequence of tokens, implemented for enums
+/// with the `lexer` attribute.
+///
+/// Unlike searching, every byte of the input must be part of a token. At each
+/// position the longest token is taken (ties go to the variant with the
+/// highest priority, then the first declared). Tokens of `skip` variants are
+/// consumed without being returned.
+pub trait Lexer: FromRegex {
+    /// Find the longest token starting at `pos` in `text`, using only the
+    /// variants active in `state` (`None` for the initial state). The text
+    /// before `pos` is kept as context, for e.g. `\b`.
+    fn lex_token(text: &str, pos: usize, state: Option<&str>) -> Option<Lexeme<Self>>;
+
+    /// Split a string into tokens, along with the byte ranges they span. The
+    /// iterator ends after the first error.
+    fn lex(s: &str) -> Lex<'_, Self> {
+        Lex {
+            text: s,
+            pos: 0,
+            states: Vec::new(),
+            failed: false,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// The patterns of a lexer's variants active in one state, compiled together
+/// to find which of them can match at a position in a single search
+#[derive(Debug, Clone)]
+pub struct LexPatterns {
+    regex: meta::Regex,
+}
+
+impl LexPatterns {
+    /// Compile the patterns (in the order their variants are tried)
+    pub fn new(patterns: &[&str]) -> Result<Self, Box<meta::BuildError>> {
+        let regex = meta::Regex::builder()
+            .configure(meta::Regex::config().match_kind(MatchKind::All))
+            .build_many(patterns)?;
+        Ok(Self { regex })
+    }
+
+    /// The indices of the patterns with a match starting at `pos` in `text`,
+    /// in ascending order
+    pub fn matching_at(&self, text: &str, pos: usize) -> Vec<usize> {
+        let input = Input::new(text).range(pos..).anchored(Anchored::Yes);
+        let mut matching = PatternSet::new(self.regex.pattern_len());
+        self.regex.which_overlapping_matches(&input, &mut matching);
+        matching.iter().map(|pattern| pattern.as_usize()).collect()
+    }
+}
+
+/// A token found by [`Lexer::lex_token`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme<T> {
+    /// Length of the token in bytes
+    pub len: usize,
+
+    /// The token
+    pub value: T,
+
+    /// Whether the token should be consumed silently
+    pub skip: bool,
+
+    /// How the lexer state changes after this token
+    pub transition: Transition,
+}
+
+/// A change of lexer state. States are kept on a stack, so that e.g. a
+/// string can be entered from several states and returns to the one it was
+/// entered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Stay in the current state
+    Stay,
+
+    /// Enter the named state
+    Push(&'static str),
+
+    /// Return to the previous state
+    Pop,
+}
+
+/// Error returned when no token matches part of the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    /// Byte position of the input that couldn't be tokenized
+    pub position: usize,
+
+    /// The lexer state at that position (`None` for the initial state)
+    pub state: Option<&'static str>,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No token matches at byte {}", self.position)?;
+        if let Some(state) = self.state {
+            write!(f, " (in state `{}`)", state)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Iterator returned by [`Lexer::lex`]
+#[derive(Debug)]
+pub struct Lex<'s, T> {
+    text: &'s str,
+    pos: usize,
+    states: Vec<&'static str>,
+    failed: bool,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'s, T: Lexer> Iterator for Lex<'s, T> {
+    type Item = Result<(Range<usize>, T), LexError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed && self.pos < self.text.len() {
+            let state = self.states.last().copied();
+
+            // Empty tokens would never advance, so are treated as no match
+            let lexeme = match T::lex_token(self.text, self.pos, state) {
+                Some(lexeme) if lexeme.len > 0 => lexeme,
+                _ => {
+                    self.failed = true;
+                    return Some(Err(LexError {
+                        position: self.pos,
+                        state,
+                    }));
+                }
+            };
+
+            let range = self.pos..self.pos + lexeme.len;
+            self.pos = range.end;
+            match lexeme.transition {
+                Transition::Stay => {}
+                Transition::Push(state) => self.states.push(state),
+                Transition::Pop => {
+                    self.states.pop();
+                }
+            }
+
+            if !lexeme.skip {
+                return Some(Ok((range, lexeme.value)));
+            }
+        }
+        None
+    }
+}
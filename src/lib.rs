@@ -6,14 +6,147 @@ pub use regex::{self, Captures, Regex};
 pub use segmap::{self, SegmentMap};
 pub use std::str::FromStr;
 
+use std::borrow::Cow;
+use std::ops::{Bound, Range, RangeBounds};
+
+mod ext;
+pub use ext::{FilterParse, ParseRegex, RegexIterExt, RegexStrExt};
+
+mod search;
+pub use search::{
+    captures_from, captures_overlapping, resume_at, Candidate, CapturesFrom, CapturesOverlapping,
+    Finder, OverlapPolicy, SearchOptions,
+};
+
+mod highlight;
+pub use highlight::{Format, Highlighter};
+
+mod incremental;
+pub use incremental::Edit;
+
+mod lexer;
+pub use lexer::{Lex, LexError, LexPatterns, Lexeme, Lexer, Transition};
+
+mod parser;
+pub use parser::{GroupRequirement, Parser, PatternError};
+
+mod scanner;
+pub use scanner::Scanner;
+
+mod token;
+pub use token::{Token, Tokens};
+
+mod tree;
+pub use tree::MatchTree;
+
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "rayon")]
+pub use par::{ParFromRegex, Split};
+
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(feature = "tokio")]
+pub use stream::{AsyncFromRegex, ParseLines, SearchRecords};
+
 // TODO: String vs &str in capture fields
 // TODO: only need clone for search. And not really even for that
 
+/// Construct an instance of this type from the captures of any regex (such
+/// as one configured at runtime), rather than its own pattern.
+///
+/// The derived implementation reads each field from the capture group named
+/// after it (`_0`, `_1`, ... for tuple fields). `Option` fields are `None` if
+/// their group didn't participate, and other fields need it to. For enums,
+/// the first variant (in priority order) whose group (named after it)
+/// participated is constructed, with its fields read from groups named as
+/// for `match_mode = "longest"` (e.g. `Variant_field`). Transparent variants
+/// parse their group's text with their inner type.
+///
+/// Transparent structs don't implement this trait.
+pub trait FromCaptures: Sized {
+    /// Construct an instance from `captures`
+    fn from_captures(captures: &Captures) -> Option<Self> {
+        Self::from_captures_prefixed(captures, "")
+    }
+
+    /// Construct an instance from `captures`, where every group name starts
+    /// with `prefix` (e.g. with a prefix of `"server_"`, field `host` is read
+    /// from group `server_host`). This lets one regex hold several values.
+    fn from_captures_prefixed(captures: &Captures, prefix: &str) -> Option<Self>;
+
+    /// The capture groups (without a prefix) `from_captures` needs from a
+    /// pattern, used to check patterns given to
+    /// [`FromCaptures::parser_with_pattern`].
+    ///
+    /// The default implementation has no requirements.
+    fn group_requirements() -> Vec<GroupRequirement> {
+        Vec::new()
+    }
+
+    /// Compile a pattern given at runtime (e.g. from a config file) to parse
+    /// and search for this type with, instead of its own. Fails if the
+    /// pattern doesn't always capture the groups that are needed (see
+    /// [`FromCaptures::group_requirements`]).
+    fn parser_with_pattern(pattern: &str) -> Result<Parser<Self>, PatternError> {
+        Parser::new(pattern)
+    }
+}
+
+/// Types with several named patterns ("dialects"), declared with
+/// `#[from_regex(pattern = "regex", dialect = "name")]`, to choose between
+/// at runtime (e.g. one log format or another).
+///
+/// Each dialect's pattern is read with [`FromCaptures`], so its groups are
+/// named as for [`FromCaptures::from_captures`], and is checked the first
+/// time a dialect is used. A struct without a pattern of its own parses with
+/// its `default_dialect` (or else its first dialect).
+pub trait Dialects: FromCaptures + 'static {
+    /// The names of the dialects, in declaration order
+    fn dialects() -> &'static [&'static str];
+
+    /// The parser for the named dialect, or `None` if there isn't one
+    fn dialect_parser(dialect: &str) -> Option<&'static Parser<Self>>;
+
+    /// Try to construct an instance of this type from a string, with the
+    /// named dialect's pattern
+    fn from_regex_dialect(s: &str, dialect: &str) -> Option<Self> {
+        Self::dialect_parser(dialect)?.parse(s)
+    }
+}
+
 /// Try to construct an instance of this type from a string
 pub trait FromRegex: Sized {
     /// Try to construct an instance of this type from a string
     fn from_regex(s: &str) -> Option<Self>;
 
+    /// Construct every instance of this type that matches the entire string.
+    /// For enums, this is one instance for each variant that matches (but
+    /// never the `default` variant).
+    ///
+    /// The default implementation returns the result of
+    /// [`FromRegex::from_regex`], if any.
+    fn from_regex_all(s: &str) -> Vec<Self> {
+        Self::from_regex(s).into_iter().collect()
+    }
+
+    /// Check whether more than one instance of this type (e.g. more than one
+    /// enum variant) matches the entire string (see
+    /// [`FromRegex::from_regex_all`])
+    fn is_ambiguous(s: &str) -> bool {
+        Self::from_regex_all(s).len() > 1
+    }
+
+    /// The name of this value's enum variant (or of its struct), e.g. for
+    /// highlighting matches by kind.
+    ///
+    /// The default implementation returns the name of the type.
+    fn variant_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
     /// Search through a string and return all instances of this type matched
     fn matches(s: &str) -> Vec<Self> {
         Self::match_locations(s)
@@ -25,6 +158,230 @@ pub trait FromRegex: Sized {
     /// Search through a string and return all instances of this type matched,
     /// As well as the ranges at which they occur.
     fn match_locations(s: &str) -> SegmentMap<usize, Self>;
+
+    /// Same as [`FromRegex::match_locations`], but only finding the matches
+    /// that start within `range` of the string, as though searching began
+    /// at its start. Patterns still see the text around the range (e.g. for
+    /// `\b`), and matches may end after it.
+    ///
+    /// The default implementation searches with [`FromRegex::finder`],
+    /// resuming after each match.
+    fn match_locations_in(s: &str, range: Range<usize>) -> SegmentMap<usize, Self>
+    where
+        Self: Clone + Eq,
+    {
+        let mut finder = Self::finder(s);
+        let mut locations = SegmentMap::new();
+        let mut pos = Some(range.start);
+        while let Some(candidate) = pos.and_then(|pos| finder.find_at(pos)) {
+            if candidate.range.start >= range.end {
+                break;
+            }
+            pos = resume_at(s, &candidate.range);
+            locations.insert_if_empty(candidate.range, candidate.value);
+        }
+        locations
+    }
+
+    /// The first match [`FromRegex::match_locations`] would find if it began
+    /// searching at byte `start` of the string (seeing the text before it as
+    /// context), or `None` if there are no more.
+    ///
+    /// The default implementation searches the entire string with
+    /// [`FromRegex::match_locations`], so prefer [`FromRegex::finder`] when
+    /// searching from several positions.
+    fn find_at(s: &str, start: usize) -> Option<Candidate<Self>> {
+        Self::finder(s).find_at(start)
+    }
+
+    /// A [`Finder`] for the matches [`FromRegex::find_at`] would find in the
+    /// string, for searching from several increasing positions (e.g. to
+    /// resume after each match).
+    ///
+    /// The default implementation searches the entire string once with
+    /// [`FromRegex::match_locations`], then skips the matches before each
+    /// position. Implementations overriding [`FromRegex::find_at`] should
+    /// override this too, to search with it.
+    fn finder<'s>(s: &'s str) -> Finder<'s, Self>
+    where
+        Self: 's,
+    {
+        let mut matches = Self::match_locations(s)
+            .into_iter()
+            .filter_map(|(range, value)| Some((to_range(&range)?, value)));
+        Finder::new(move |start| {
+            matches
+                .by_ref()
+                .find(|(range, _)| range.start >= start)
+                .map(|(range, value)| Candidate {
+                    range,
+                    priority: 0,
+                    value,
+                })
+        })
+    }
+
+    /// Split a string into the matches found by
+    /// [`FromRegex::match_locations`] and the unmatched gaps between them.
+    /// Together, the tokens cover the entire string in order.
+    fn tokenize(s: &str) -> Tokens<'_, Self> {
+        Tokens::new(s, Self::match_locations(s))
+    }
+
+    /// Search through a string for the matches of
+    /// [`FromRegex::match_locations`], each holding the other matches found
+    /// within it (see [`FromRegex::match_all_overlapping`]), e.g. those of a
+    /// transparent variant's inner components.
+    fn match_tree(s: &str) -> Vec<MatchTree<Self>>
+    where
+        Self: Clone + Eq,
+    {
+        let roots = Self::match_locations(s)
+            .into_iter()
+            .filter_map(|(range, value)| Some((to_range(&range)?, value)))
+            .collect();
+        tree::grow(roots, Self::match_all_overlapping(s))
+    }
+
+    /// Search for `Inner` only within the matches of this type, returning
+    /// its matches (with ranges in the entire string), in order. `Inner`'s
+    /// patterns still see the text around each match (e.g. for `\b`).
+    fn search_within<Inner: FromRegex>(s: &str) -> Vec<(Range<usize>, Inner)> {
+        let mut finder = Inner::finder(s);
+        // A match after the last outer match, which may be in the next one
+        let mut next: Option<Candidate<Inner>> = None;
+        let mut found = Vec::new();
+        for (range, _) in Self::match_locations(s) {
+            let outer = match to_range(&range) {
+                Some(outer) => outer,
+                None => continue,
+            };
+            let mut pos = Some(outer.start);
+            while let Some(start) = pos {
+                let candidate = match next.take() {
+                    Some(candidate) if candidate.range.start >= start => candidate,
+                    _ => match finder.find_at(start) {
+                        Some(candidate) => candidate,
+                        None => break,
+                    },
+                };
+                if candidate.range.start >= outer.end {
+                    next = Some(candidate);
+                    break;
+                }
+
+                // Matches ending after the outer match are dropped
+                pos = resume_at(s, &candidate.range);
+                if candidate.range.end <= outer.end {
+                    found.push((candidate.range, candidate.value));
+                }
+            }
+        }
+        found
+    }
+
+    /// Replace every match found by [`FromRegex::match_locations`] with the
+    /// text returned by `replace`, keeping the text between matches
+    fn replace_all<'r, F>(s: &str, mut replace: F) -> Cow<'_, str>
+    where
+        F: FnMut(&Self) -> Cow<'r, str>,
+    {
+        let locations = Self::match_locations(s);
+        if locations.is_empty() {
+            return Cow::Borrowed(s);
+        }
+
+        let mut replaced = String::with_capacity(s.len());
+        for token in Tokens::new(s, locations) {
+            match token {
+                Token::Match(_, value) => replaced.push_str(&replace(&value)),
+                Token::Gap(_, gap) => replaced.push_str(gap),
+            }
+        }
+        Cow::Owned(replaced)
+    }
+
+    /// Replace every match found by [`FromRegex::match_locations`] with
+    /// `placeholder`, keeping the text between matches
+    fn redact<'s>(s: &'s str, placeholder: &str) -> Cow<'s, str> {
+        Self::replace_all(s, |_| Cow::Borrowed(placeholder))
+    }
+
+    /// Update the result of [`FromRegex::match_locations`] for some text
+    /// after an edit to it, where `s` is the edited text. Only the text near
+    /// the edit is searched again (see [`FromRegex::max_match_len`]), and the
+    /// matches after it are moved.
+    fn update_locations(
+        old: SegmentMap<usize, Self>,
+        edit: &Edit<'_>,
+        s: &str,
+    ) -> SegmentMap<usize, Self>
+    where
+        Self: Clone + Eq,
+    {
+        incremental::update(old, edit, s)
+    }
+
+    /// An upper bound on the length of a match in bytes (including any
+    /// context its pattern looks at, such as `\b`), set with the `max_len`
+    /// attribute. Without one, [`FromRegex::update_locations`] searches the
+    /// entire text again.
+    ///
+    /// The default implementation returns `None`.
+    fn max_match_len() -> Option<usize> {
+        None
+    }
+
+    /// Same as [`FromRegex::match_locations`], but with options (such as how
+    /// overlapping matches are resolved) chosen by the caller
+    fn match_locations_with(s: &str, options: &SearchOptions<Self>) -> SegmentMap<usize, Self>
+    where
+        Self: Clone + Eq,
+    {
+        match options.overlap_policy() {
+            Some(policy) => policy.resolve(Self::match_candidates(s)),
+            None => Self::match_locations(s),
+        }
+    }
+
+    /// Search through a string and return every match of every variant (or
+    /// of this type, for structs), including overlapping ones, ordered by
+    /// where they start.
+    ///
+    /// The default implementation returns the results of
+    /// [`FromRegex::match_candidates`].
+    fn match_all_overlapping(s: &str) -> Vec<(Range<usize>, Self)> {
+        Self::match_candidates(s)
+            .into_iter()
+            .map(|candidate| (candidate.range, candidate.value))
+            .collect()
+    }
+
+    /// Search through a string and return every match found, before overlaps
+    /// between them are resolved.
+    ///
+    /// The default implementation returns the results of
+    /// [`FromRegex::match_locations`], which never overlap.
+    fn match_candidates(s: &str) -> Vec<Candidate<Self>> {
+        Self::match_locations(s)
+            .into_iter()
+            .filter_map(|(range, value)| {
+                Some(Candidate {
+                    range: to_range(&range)?,
+                    priority: 0,
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// The patterns of the regexes used to search for this type (any match
+    /// found by searching is a match of one of them), or `None` if unknown.
+    ///
+    /// The default implementation returns `None`.
+    fn search_patterns() -> Option<Vec<String>> {
+        None
+    }
 }
 
 // TODO: Search trait? to split matches/match_locations out...
@@ -37,6 +394,54 @@ pub trait FromRegex: Sized {
 //     }
 // }
 
+/// The patterns `T` searches with (see [`FromRegex::search_patterns`]) as one
+/// alternation, with named groups made non-capturing so that it can be
+/// embedded in a larger pattern. `None` if the patterns are unknown.
+pub fn non_capturing_pattern<T: FromRegex>() -> Option<String> {
+    let patterns = T::search_patterns()?
+        .iter()
+        // Patterns that can't be parsed are kept, to fail when compiled
+        .map(|pattern| {
+            from_regex_syntax::map_group_names(pattern, |_| None)
+                .unwrap_or_else(|_| pattern.clone())
+        })
+        .collect::<Vec<_>>();
+    Some(format!("(?:{})", patterns.join("|")))
+}
+
+/// Check that `value` is parsed back from its `Display` output (e.g. one
+/// derived with `#[from_regex(display = "...")]`), panicking with the text
+/// if not. Meant for tests.
+pub fn assert_round_trip<T>(value: &T)
+where
+    T: FromRegex + std::fmt::Display + std::fmt::Debug + PartialEq,
+{
+    let text = value.to_string();
+    let parsed = T::from_regex(&text);
+    assert!(
+        parsed.as_ref() == Some(value),
+        "{:?} was displayed as {:?}, which parses as {:?}",
+        value,
+        text,
+        parsed
+    );
+}
+
+/// Convert a bounded range into a half open `start..end` range
+pub(crate) fn to_range<R: RangeBounds<usize>>(range: &R) -> Option<Range<usize>> {
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start + 1,
+        Bound::Unbounded => return None,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => end + 1,
+        Bound::Excluded(end) => *end,
+        Bound::Unbounded => return None,
+    };
+    Some(start..end)
+}
+
 pub trait TextMap<V> {
     fn merge_only_longest<I: IntoIterator<Item = (segmap::Segment<usize>, V)>>(&mut self, other: I);
 }
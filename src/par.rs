@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::{FromRegex, Regex, SegmentMap};
+
+/// Number of chunks to aim for per rayon worker thread, so uneven chunks
+/// still balance out across the pool
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Positions at which input may be split into independently searchable chunks
+#[derive(Default)]
+pub enum Split<'a> {
+    /// Split after every line break (`\n`)
+    #[default]
+    Lines,
+
+    /// Split after every match of the given pattern. No match of the searched
+    /// type may span one of these matches, or it will be missed (or found as
+    /// two separate, partial matches).
+    Pattern(&'a Regex),
+}
+
+impl<'a> Split<'a> {
+    /// Byte offsets (in `s`) immediately following each separator
+    fn boundaries<'s>(&'s self, s: &'s str) -> Box<dyn Iterator<Item = usize> + 's> {
+        match self {
+            Split::Lines => Box::new(s.match_indices('\n').map(|(i, _)| i + 1)),
+            Split::Pattern(regex) => Box::new(regex.find_iter(s).map(|mat| mat.end())),
+        }
+    }
+
+    /// Group the input into contiguous chunks ending on a split boundary,
+    /// each at least `s.len() / (threads * CHUNKS_PER_THREAD)` bytes long
+    /// (other than the last)
+    fn chunks(&self, s: &str) -> Vec<Range<usize>> {
+        let min_len = s.len() / (rayon::current_num_threads() * CHUNKS_PER_THREAD);
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for end in self.boundaries(s) {
+            if end - start > min_len {
+                chunks.push(start..end);
+                start = end;
+            }
+        }
+        if start < s.len() || chunks.is_empty() {
+            chunks.push(start..s.len());
+        }
+        chunks
+    }
+}
+
+/// Parallel versions of [`FromRegex`] search and parsing methods (requires
+/// the `rayon` feature).
+///
+/// This is implemented for every [`FromRegex`] type that can be sent between
+/// threads.
+pub trait ParFromRegex: FromRegex + Send {
+    /// Same as [`FromRegex::match_locations`], but splits `s` at the given
+    /// boundaries and searches each chunk in parallel. Ranges in the returned
+    /// map are offsets into the whole of `s`.
+    fn par_match_locations(s: &str, split: Split) -> SegmentMap<usize, Self>
+    where
+        Self: Clone + Eq,
+    {
+        let found = split
+            .chunks(s)
+            .into_par_iter()
+            .map(|chunk| {
+                let offset = chunk.start;
+                Self::match_locations(&s[chunk])
+                    .into_iter()
+                    .filter_map(|(range, value)| {
+                        let range = crate::to_range(&range)?;
+                        Some((range.start + offset..range.end + offset, value))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Chunks don't overlap, so neither will any of their matches
+        let mut ranges = SegmentMap::new();
+        for (range, value) in found.into_iter().flatten() {
+            ranges.insert_if_empty(range, value);
+        }
+        ranges
+    }
+
+    /// Same as [`FromRegex::matches`], but searches chunks of `s` in parallel
+    /// (see [`ParFromRegex::par_match_locations`])
+    fn par_matches(s: &str, split: Split) -> Vec<Self>
+    where
+        Self: Clone + Eq,
+    {
+        Self::par_match_locations(s, split)
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    /// Try to construct an instance of this type from each line of `s`, in
+    /// parallel. The output has one entry per line, in order.
+    fn par_parse_lines(s: &str) -> Vec<Option<Self>> {
+        s.par_lines().map(Self::from_regex).collect()
+    }
+}
+
+impl<T: FromRegex + Send> ParFromRegex for T {}
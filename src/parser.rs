@@ -0,0 +1,128 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use from_regex_syntax::{check_requirements, participation, RequirementError};
+use regex::Regex;
+
+use crate::{FromCaptures, SegmentMap};
+
+/// Capture groups a [`FromCaptures`] type needs from a pattern (see
+/// [`FromCaptures::group_requirements`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupRequirement {
+    /// The group these requirements apply within (e.g. an enum variant's),
+    /// or `None` for the whole pattern. If the pattern doesn't have this
+    /// group, the requirements don't apply.
+    pub within: Option<&'static str>,
+
+    /// Groups that must participate in every match of `within`
+    pub groups: Vec<&'static str>,
+}
+
+/// Why a pattern can't be used by a [`Parser`]
+#[derive(Debug, Clone)]
+pub enum PatternError {
+    /// The pattern doesn't compile
+    Regex(regex::Error),
+
+    /// A group needed to construct a value isn't in the pattern
+    MissingGroup(&'static str),
+
+    /// A group needed to construct a value might not participate in a match
+    /// (e.g. it's in `(...)?`, or one side of an alternation)
+    OptionalGroup(&'static str),
+
+    /// The pattern has none of the groups of an enum's variants, so nothing
+    /// can be constructed from it
+    NoVariants,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Regex(err) => write!(f, "Invalid pattern: {}", err),
+            PatternError::MissingGroup(group) => {
+                write!(f, "The pattern has no group named `{}`", group)
+            }
+            PatternError::OptionalGroup(group) => {
+                write!(f, "The group `{}` might not participate in a match", group)
+            }
+            PatternError::NoVariants => write!(f, "The pattern has no group for any variant"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatternError::Regex(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Parses and searches for a [`FromCaptures`] type with a pattern given at
+/// runtime (see [`FromCaptures::parser_with_pattern`]), instead of its own
+#[derive(Debug, Clone)]
+pub struct Parser<T> {
+    regex: Regex,
+    full_regex: Regex,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: FromCaptures> Parser<T> {
+    /// Compile `pattern`, checking that it has every group `T` needs
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let regex = Regex::new(pattern).map_err(PatternError::Regex)?;
+        let full_regex =
+            Regex::new(&format!(r"\A(?:{})\z", pattern)).map_err(PatternError::Regex)?;
+
+        // The regex crate parses with the same defaults, so this only fails
+        // if it would have too
+        let hir = regex_syntax::parse(pattern)
+            .map_err(|err| PatternError::Regex(regex::Error::Syntax(err.to_string())))?;
+        let requirements = T::group_requirements();
+        check_requirements(
+            requirements
+                .iter()
+                .map(|requirement| (requirement.within, requirement.groups.iter().copied())),
+            &participation(&hir),
+        )
+        .map_err(|err| match err {
+            RequirementError::MissingGroup(group) => PatternError::MissingGroup(group),
+            RequirementError::OptionalGroup(group) => PatternError::OptionalGroup(group),
+            RequirementError::NoVariants => PatternError::NoVariants,
+        })?;
+
+        Ok(Self {
+            regex,
+            full_regex,
+            marker: PhantomData,
+        })
+    }
+
+    /// The compiled pattern
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Try to construct a value from the entire string
+    pub fn parse(&self, s: &str) -> Option<T> {
+        T::from_captures(&self.full_regex.captures(s)?)
+    }
+}
+
+impl<T: FromCaptures + Clone + Eq> Parser<T> {
+    /// Search through a string and return all values found, along with the
+    /// ranges at which they occur
+    pub fn match_locations(&self, s: &str) -> SegmentMap<usize, T> {
+        let mut locations = SegmentMap::new();
+        for captures in self.regex.captures_iter(s) {
+            let range = captures.get(0).unwrap().range();
+            if let Some(value) = T::from_captures(&captures) {
+                locations.insert_if_empty(range, value);
+            }
+        }
+        locations
+    }
+}
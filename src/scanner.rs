@@ -0,0 +1,141 @@
+use std::any::Any;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use regex::RegexSet;
+
+use crate::{resume_at, Candidate, Finder, FromRegex};
+
+/// Search for several [`FromRegex`] types at once
+///
+/// ```ignore
+/// let scanner = Scanner::new().with::<Ipv4>().with::<Email>();
+/// for (range, value) in scanner.scan(text) {
+///     if let Some(email) = value.downcast_ref::<Email>() { /* ... */ }
+/// }
+/// ```
+///
+/// Matches can also be collected into an enum with a variant per type:
+///
+/// ```ignore
+/// let scanner = Scanner::<Token>::default()
+///     .with_map(Token::Ipv4)
+///     .with_map(Token::Email);
+/// ```
+///
+/// The patterns of every type are compiled into one [`RegexSet`], which
+/// decides the types to search for (types without a match anywhere in the
+/// input aren't searched). Overlapping matches (including those of different
+/// types) are resolved as for [`OverlapPolicy::LeftmostLongest`], with ties
+/// going to the type registered first, and each type's search resumes after
+/// the last match kept.
+pub struct Scanner<O = Box<dyn Any>> {
+    searchers: Vec<Searcher<O>>,
+    patterns: Vec<String>,
+    set: OnceLock<Option<RegexSet>>,
+}
+
+/// A type registered with a [`Scanner`]
+struct Searcher<O> {
+    /// Indexes of the type's patterns in the scanner's set, or `None` if its
+    /// patterns are unknown (so it's always searched)
+    patterns: Option<Range<usize>>,
+
+    /// Create a finder for the type's matches in a string, converted to the
+    /// scanner's output
+    #[allow(clippy::type_complexity)]
+    finder: Box<dyn for<'s> Fn(&'s str) -> Finder<'s, O> + Send + Sync>,
+}
+
+impl Scanner {
+    /// Create a scanner returning matches as [`Box<dyn Any>`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also search for `T`
+    pub fn with<T: FromRegex + 'static>(self) -> Self {
+        self.with_map(|value: T| Box::new(value) as Box<dyn Any>)
+    }
+}
+
+impl<O> Default for Scanner<O> {
+    fn default() -> Self {
+        Self {
+            searchers: Vec::new(),
+            patterns: Vec::new(),
+            set: OnceLock::new(),
+        }
+    }
+}
+
+impl<O> Scanner<O> {
+    /// Also search for `T`, converting its matches with `map` (e.g. an enum
+    /// variant's constructor)
+    pub fn with_map<T, F>(mut self, map: F) -> Self
+    where
+        T: FromRegex + 'static,
+        F: Fn(T) -> O + Send + Sync + 'static,
+    {
+        let map = Arc::new(map);
+        let patterns = T::search_patterns().map(|patterns| {
+            let start = self.patterns.len();
+            self.patterns.extend(patterns);
+            start..self.patterns.len()
+        });
+        self.searchers.push(Searcher {
+            patterns,
+            finder: Box::new(move |s| {
+                let map = Arc::clone(&map);
+                let mut finder = T::finder(s);
+                Finder::new(move |start| {
+                    finder.find_at(start).map(|candidate| Candidate {
+                        range: candidate.range,
+                        priority: candidate.priority,
+                        value: map(candidate.value),
+                    })
+                })
+            }),
+        });
+        self.set = OnceLock::new();
+        self
+    }
+
+    /// Search through a string and return all matches of the registered
+    /// types, along with the ranges at which they occur (in order)
+    pub fn scan(&self, s: &str) -> Vec<(Range<usize>, O)> {
+        // If the set can't be compiled, every type is searched
+        let set = self
+            .set
+            .get_or_init(|| RegexSet::new(&self.patterns).ok())
+            .as_ref();
+        let matched = set.map(|set| set.matches(s));
+
+        let searchers = self
+            .searchers
+            .iter()
+            .filter(|searcher| match (&searcher.patterns, &matched) {
+                (Some(patterns), Some(matched)) => {
+                    patterns.clone().any(|pattern| matched.matched(pattern))
+                }
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+
+        // Each type's next match is kept until a match overlapping it is
+        // chosen, when it's searched for again after that match
+        let mut finder = Finder::leftmost_longest(
+            searchers
+                .iter()
+                .map(|searcher| (searcher.finder)(s))
+                .collect(),
+        );
+        let mut hits = Vec::new();
+        let mut pos = Some(0);
+        while let Some(Candidate { range, value, .. }) = pos.and_then(|pos| finder.find_at(pos)) {
+            pos = resume_at(s, &range);
+            hits.push((range, value));
+        }
+        hits
+    }
+}
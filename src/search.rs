@@ -0,0 +1,283 @@
+use std::cmp::{Ordering, Reverse};
+use std::ops::Range;
+
+use crate::{Captures, Regex, SegmentMap};
+
+/// A match found while searching, before overlaps with other matches have
+/// been resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate<T> {
+    /// Byte range of the match in the searched text
+    pub range: Range<usize>,
+
+    /// Priority of the variant that matched (`0` unless set with the
+    /// `priority` attribute)
+    pub priority: i64,
+
+    /// The matched value
+    pub value: T,
+}
+
+/// How to choose between overlapping candidate matches while searching
+///
+/// Candidates are ordered by the policy, then kept one at a time unless they
+/// overlap a candidate that was already kept. Ties keep the order in which
+/// candidates were found (variants in priority, then declaration, order).
+pub enum OverlapPolicy<T> {
+    /// Keep whichever candidate was found first (the default for
+    /// `match_mode = "first"` and for structs)
+    FirstFound,
+
+    /// Keep the candidate that starts first
+    Leftmost,
+
+    /// Keep the candidate that starts first, or the longer of two that start
+    /// at the same position (the default for `match_mode = "longest"`)
+    LeftmostLongest,
+
+    /// Keep the longest candidate
+    Longest,
+
+    /// Keep the candidate with the highest priority, then as for
+    /// [`OverlapPolicy::LeftmostLongest`]
+    HighestPriority,
+
+    /// Order candidates with the given comparison, keeping the least
+    #[allow(clippy::type_complexity)]
+    Custom(Box<dyn Fn(&Candidate<T>, &Candidate<T>) -> Ordering + Send + Sync>),
+}
+
+impl<T> OverlapPolicy<T> {
+    /// Order two candidates by the policy, where the lesser is kept if they
+    /// overlap (and ties keep whichever was found first)
+    pub fn compare(&self, a: &Candidate<T>, b: &Candidate<T>) -> Ordering {
+        let key = |c: &Candidate<T>| (c.range.start, Reverse(c.range.len()));
+        match self {
+            OverlapPolicy::FirstFound => Ordering::Equal,
+            OverlapPolicy::Leftmost => a.range.start.cmp(&b.range.start),
+            OverlapPolicy::LeftmostLongest => key(a).cmp(&key(b)),
+            OverlapPolicy::Longest => (Reverse(a.range.len()), a.range.start)
+                .cmp(&(Reverse(b.range.len()), b.range.start)),
+            OverlapPolicy::HighestPriority => {
+                (Reverse(a.priority), key(a)).cmp(&(Reverse(b.priority), key(b)))
+            }
+            OverlapPolicy::Custom(compare) => compare(a, b),
+        }
+    }
+
+    /// Choose the non-overlapping matches to keep from a set of candidates
+    pub fn resolve<I>(&self, candidates: I) -> SegmentMap<usize, T>
+    where
+        T: Clone + Eq,
+        I: IntoIterator<Item = Candidate<T>>,
+    {
+        let mut candidates = candidates.into_iter().collect::<Vec<_>>();
+
+        // Sorts are stable, so ties stay in the order they were found
+        candidates.sort_by(|a, b| self.compare(a, b));
+
+        let mut ranges = SegmentMap::new();
+        for Candidate { range, value, .. } in candidates {
+            ranges.insert_if_empty(range, value);
+        }
+        ranges
+    }
+}
+
+/// Options for [`FromRegex::match_locations_with`](crate::FromRegex::match_locations_with)
+pub struct SearchOptions<T> {
+    policy: Option<OverlapPolicy<T>>,
+}
+
+impl<T> SearchOptions<T> {
+    /// Search with the type's default options (the same as
+    /// [`FromRegex::match_locations`](crate::FromRegex::match_locations))
+    pub fn new() -> Self {
+        Self { policy: None }
+    }
+
+    /// Resolve overlapping matches with the given policy
+    pub fn policy(mut self, policy: OverlapPolicy<T>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// The policy to resolve overlapping matches with, if not the type's
+    /// default
+    pub fn overlap_policy(&self) -> Option<&OverlapPolicy<T>> {
+        self.policy.as_ref()
+    }
+}
+
+impl<T> Default for SearchOptions<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterate over the captures of the (non-overlapping) matches of `regex` in
+/// `text`, like [`Regex::captures_iter`], but starting at byte `start`. The
+/// text before it is still seen by the pattern (e.g. for `\b`).
+pub fn captures_from<'r, 's>(
+    regex: &'r Regex,
+    text: &'s str,
+    start: usize,
+) -> CapturesFrom<'r, 's> {
+    CapturesFrom {
+        regex,
+        text,
+        pos: Some(start),
+    }
+}
+
+/// Iterator returned by [`captures_from`]
+#[derive(Debug)]
+pub struct CapturesFrom<'r, 's> {
+    regex: &'r Regex,
+    text: &'s str,
+    pos: Option<usize>,
+}
+
+impl<'r, 's> Iterator for CapturesFrom<'r, 's> {
+    type Item = Captures<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let captures = self.regex.captures_at(self.text, self.pos?);
+        self.pos = captures
+            .as_ref()
+            .and_then(|cap| resume_at(self.text, &cap.get(0).unwrap().range()));
+        captures
+    }
+}
+
+/// Iterate over the captures of every match of `regex` in `text`, including
+/// overlapping ones. At most one match (the leftmost-first one) is found
+/// starting at each position.
+pub fn captures_overlapping<'r, 's>(
+    regex: &'r Regex,
+    text: &'s str,
+) -> CapturesOverlapping<'r, 's> {
+    CapturesOverlapping {
+        regex,
+        text,
+        pos: Some(0),
+    }
+}
+
+/// Iterator returned by [`captures_overlapping`]
+#[derive(Debug)]
+pub struct CapturesOverlapping<'r, 's> {
+    regex: &'r Regex,
+    text: &'s str,
+    pos: Option<usize>,
+}
+
+impl<'r, 's> Iterator for CapturesOverlapping<'r, 's> {
+    type Item = Captures<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let captures = self.regex.captures_at(self.text, self.pos?);
+
+        // Resume searching from the character after this match's start
+        self.pos = captures.as_ref().and_then(|cap| {
+            let start = cap.get(0).unwrap().start();
+            self.text[start..]
+                .chars()
+                .next()
+                .map(|c| start + c.len_utf8())
+        });
+        captures
+    }
+}
+
+/// Where to resume searching after a match at `range` (after the character
+/// following it, if it's empty), or `None` at the end of the text. Useful
+/// for implementing [`FromRegex::find_at`](crate::FromRegex::find_at).
+pub fn resume_at(text: &str, range: &Range<usize>) -> Option<usize> {
+    if !range.is_empty() {
+        return Some(range.end);
+    }
+    text[range.end..]
+        .chars()
+        .next()
+        .map(|c| range.end + c.len_utf8())
+}
+
+/// Finds the matches of a type in a string, at increasing positions (see
+/// [`FromRegex::finder`](crate::FromRegex::finder))
+///
+/// Each position searched from must be after the start of the last match
+/// found, as when resuming with [`resume_at`].
+pub struct Finder<'s, T> {
+    #[allow(clippy::type_complexity)]
+    find_at: Box<dyn FnMut(usize) -> Option<Candidate<T>> + 's>,
+}
+
+impl<'s, T> Finder<'s, T> {
+    /// Create a finder from a function returning the first match starting at
+    /// or after a position
+    pub fn new<F>(find_at: F) -> Self
+    where
+        F: FnMut(usize) -> Option<Candidate<T>> + 's,
+    {
+        Self {
+            find_at: Box::new(find_at),
+        }
+    }
+
+    /// Combine several finders, finding the leftmost (then longest) of their
+    /// next matches, with ties going to the earlier finder.
+    ///
+    /// Each finder's next match is kept until a later position is searched
+    /// from, so a finder is only searched again once its match has been
+    /// found or overlaps the last one found. This keeps searching an entire
+    /// string linear in its length.
+    pub fn leftmost_longest(finders: Vec<Finder<'s, T>>) -> Self
+    where
+        T: 's,
+    {
+        // Each finder's next match (`None` if it hasn't been searched for)
+        let mut next = finders
+            .into_iter()
+            .map(|finder| (finder, None))
+            .collect::<Vec<(Finder<'s, T>, Option<Option<Candidate<T>>>)>>();
+        Self::new(move |start| {
+            let mut best: Option<usize> = None;
+            for i in 0..next.len() {
+                let (finder, candidate) = &mut next[i];
+                let stale = match candidate {
+                    Some(Some(candidate)) => candidate.range.start < start,
+                    Some(None) => false,
+                    None => true,
+                };
+                if stale {
+                    *candidate = Some(finder.find_at(start));
+                }
+
+                if let Some(Some(candidate)) = &next[i].1 {
+                    let better = match best.and_then(|best| next[best].1.as_ref()?.as_ref()) {
+                        Some(best) => {
+                            OverlapPolicy::LeftmostLongest.compare(candidate, best)
+                                == Ordering::Less
+                        }
+                        None => true,
+                    };
+                    if better {
+                        best = Some(i);
+                    }
+                }
+            }
+            next[best?].1.take().flatten()
+        })
+    }
+
+    /// The first match starting at or after byte `start`, or `None` if there
+    /// are no more
+    pub fn find_at(&mut self, start: usize) -> Option<Candidate<T>> {
+        (self.find_at)(start)
+    }
+}
+
+impl<'s, T> std::fmt::Debug for Finder<'s, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Finder").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::ready;
+use futures_util::stream::Stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+use crate::FromRegex;
+
+/// Streaming versions of [`FromRegex`] parsing and search methods, reading
+/// from a tokio [`AsyncBufRead`] (requires the `tokio` feature).
+///
+/// This is implemented for every [`FromRegex`] type. Streams end after the
+/// first I/O error they yield.
+pub trait AsyncFromRegex: FromRegex {
+    /// Try to construct an instance of this type from each line read from
+    /// `reader`. The stream has one entry per line, in order.
+    ///
+    /// Lines must be valid UTF-8 and have their line endings (`\n` or `\r\n`)
+    /// removed before parsing.
+    fn parse_lines<R>(reader: R) -> ParseLines<R, Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        ParseLines {
+            lines: reader.lines(),
+            done: false,
+            _type: PhantomData,
+        }
+    }
+
+    /// Read records separated by `delimiter` from `reader` and search through
+    /// each of them, yielding every instance of this type matched (see
+    /// [`FromRegex::matches`]).
+    ///
+    /// Matches never span records, and the delimiter itself is not searched.
+    /// Records must be valid UTF-8.
+    fn search_records<R>(reader: R, delimiter: u8) -> SearchRecords<R, Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        SearchRecords {
+            reader,
+            delimiter,
+            buf: Vec::new(),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<T: FromRegex> AsyncFromRegex for T {}
+
+/// Stream returned by [`AsyncFromRegex::parse_lines`]
+#[derive(Debug)]
+pub struct ParseLines<R, T> {
+    lines: Lines<R>,
+    done: bool,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<R: AsyncBufRead + Unpin, T: FromRegex> Stream for ParseLines<R, T> {
+    type Item = io::Result<Option<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        match ready!(Pin::new(&mut this.lines).poll_next_line(cx)) {
+            Ok(Some(line)) => Poll::Ready(Some(Ok(T::from_regex(&line)))),
+            Ok(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Err(err) => {
+                this.done = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+/// Stream returned by [`AsyncFromRegex::search_records`]
+#[derive(Debug)]
+pub struct SearchRecords<R, T> {
+    reader: R,
+    delimiter: u8,
+    buf: Vec<u8>,
+    pending: VecDeque<T>,
+    done: bool,
+}
+
+// Queued values are never pinned, so only the reader needs to be `Unpin`
+impl<R: Unpin, T> Unpin for SearchRecords<R, T> {}
+
+impl<R: AsyncBufRead + Unpin, T: FromRegex> SearchRecords<R, T> {
+    /// Read until the end of the next record, keeping partial records in the
+    /// buffer across polls. Returns `false` at the end of the input.
+    fn poll_record(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        let delimiter = self.delimiter;
+        loop {
+            let available = ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?;
+            if available.is_empty() {
+                return Poll::Ready(Ok(!self.buf.is_empty()));
+            }
+
+            match available.iter().position(|&b| b == delimiter) {
+                Some(i) => {
+                    self.buf.extend_from_slice(&available[..i]);
+                    Pin::new(&mut self.reader).consume(i + 1);
+                    return Poll::Ready(Ok(true));
+                }
+                None => {
+                    let len = available.len();
+                    self.buf.extend_from_slice(available);
+                    Pin::new(&mut self.reader).consume(len);
+                }
+            }
+        }
+    }
+
+    /// Search the buffered record and queue its matches
+    fn search(&mut self) -> io::Result<()> {
+        let record = std::str::from_utf8(&self.buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.pending.extend(T::matches(record));
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<R: AsyncBufRead + Unpin, T: FromRegex> Stream for SearchRecords<R, T> {
+    type Item = io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(value) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(value)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match ready!(this.poll_record(cx)).and_then(|more| {
+                if more {
+                    this.search()?;
+                }
+                Ok(more)
+            }) {
+                Ok(true) => {}
+                Ok(false) => {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
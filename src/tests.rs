@@ -1,5 +1,6 @@
 use super::*;
 use crate as from_regex;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
 #[from_regex(pattern = "abc(?P<named>def)")]
@@ -77,6 +78,307 @@ lazy_static! {
     };
 }
 
+/// Variants are declared shortest first, so the longest match is never the
+/// first alternative (but no input matches both)
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(ambiguity = "deny")]
+enum PrefixEnum {
+    #[from_regex(pattern = "a")]
+    Short,
+
+    #[from_regex(pattern = "ab")]
+    Long,
+
+    #[from_regex(transparent)]
+    Nested(MyStruct),
+}
+
+/// Variants whose matches overlap, though each can follow the other
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum ChainEnum {
+    #[from_regex(pattern = "ab")]
+    A,
+
+    #[from_regex(pattern = "bb")]
+    B,
+}
+
+/// Overlapping variants, where ties are settled by priority rather than
+/// declaration order
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum PriorityEnum {
+    #[from_regex(pattern = "[a-z]+", priority = -1)]
+    Identifier,
+
+    #[from_regex(pattern = "if|else")]
+    Keyword,
+
+    #[from_regex(transparent, priority = 1)]
+    Nested(MyStruct),
+}
+
+/// Transparent variants with named, multiple and boxed fields
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum ShapesEnum {
+    #[from_regex(transparent)]
+    Named { inner: MyStruct },
+
+    #[from_regex(transparent)]
+    Tagged(
+        #[from_regex(default = "String::from(\"prefix\")")] String,
+        #[from_regex(transparent)] Box<PrefixEnum>,
+        u32,
+    ),
+
+    #[from_regex(transparent)]
+    Shared(std::sync::Arc<SortedEnum>),
+}
+
+/// Newtype around another `FromRegex` type
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(transparent)]
+struct NestedNewtype(NestedEnum);
+
+/// Newtype that only accepts keywords
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(transparent, validate = "KeywordNewtype::is_keyword")]
+struct KeywordNewtype(PriorityEnum);
+
+impl KeywordNewtype {
+    fn is_keyword(&self) -> bool {
+        self.0 == PriorityEnum::Keyword
+    }
+}
+
+/// Default variants receiving the unmatched input
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum InputEnum {
+    #[from_regex(pattern = "c")]
+    Known,
+
+    #[from_regex(default)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum LabeledEnum {
+    #[from_regex(pattern = "c")]
+    Known,
+
+    #[from_regex(default)]
+    Other {
+        #[from_regex(input)]
+        text: String,
+        count: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum ConstructedEnum {
+    #[from_regex(pattern = "c")]
+    Known,
+
+    #[from_regex(default = "ConstructedEnum::unknown")]
+    Unknown(usize),
+}
+
+impl ConstructedEnum {
+    fn unknown(s: &str) -> Self {
+        Self::Unknown(s.len())
+    }
+}
+
+/// Default variants constructed by a closure, and receiving the input in a
+/// fully qualified `String`
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum ClosureEnum {
+    #[from_regex(pattern = "c")]
+    Known,
+
+    #[from_regex(default = "|s: &str| ClosureEnum::Unknown(s.chars().count())")]
+    Unknown(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum QualifiedInputEnum {
+    #[from_regex(pattern = "c")]
+    Known,
+
+    #[from_regex(default)]
+    Other(std::string::String),
+}
+
+/// Lexer telling units (attached to a number) from words by the boundary
+/// before them
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(lexer)]
+enum UnitLexer {
+    #[from_regex(pattern = r"\s+", skip)]
+    Whitespace,
+
+    #[from_regex(pattern = "[0-9]+")]
+    Number,
+
+    #[from_regex(pattern = r"\B[a-z]+")]
+    Unit,
+
+    #[from_regex(pattern = r"\b[a-z]+")]
+    Word,
+}
+
+/// Variants whose groups start with an underscore
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum UnderscoreEnum {
+    #[from_regex(pattern = "(?P<_kind>[a-z]+)!")]
+    Hidden { _kind: String },
+
+    #[from_regex(pattern = "(?P<_0>[a-z])(?P<_1>[0-9])")]
+    Pair(String, String),
+}
+
+/// Variants whose groups are named with the `(?<name>...)` syntax
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum AngleGroupEnum {
+    #[from_regex(pattern = r"(?<_0>\d+)")]
+    Number(String),
+
+    #[from_regex(pattern = r"(?<name>[a-z]+)!")]
+    Named { name: String },
+}
+
+/// Lexer with a separate state for the inside of strings
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(lexer)]
+enum LexerEnum {
+    #[from_regex(pattern = r"\s+", skip)]
+    Whitespace,
+
+    #[from_regex(pattern = "if", priority = 1)]
+    If,
+
+    #[from_regex(pattern = "(?P<name>[a-z]+)")]
+    Identifier { name: String },
+
+    #[from_regex(pattern = "\"", push = "string")]
+    Quote,
+
+    #[from_regex(pattern = r#"(?P<text>[^"\\]+)"#, state = "string")]
+    Text { text: String },
+
+    #[from_regex(pattern = r"\\(?P<escaped>.)", state = "string")]
+    Escape { escaped: String },
+
+    #[from_regex(pattern = "\"", state = "string", pop)]
+    EndQuote,
+}
+
+/// Struct assembled from its fields' patterns
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(separator = r"\s+")]
+struct Command {
+    #[from_regex(pattern = r"[a-z]+")]
+    name: String,
+
+    #[from_regex(pattern = r"\d+")]
+    count: u32,
+
+    target: SortedEnum,
+}
+
+/// Struct assembled with a separator that's an alternation
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(separator = ",|;")]
+struct SeparatedPair {
+    #[from_regex(pattern = r"\d+")]
+    a: u32,
+
+    #[from_regex(pattern = r"\d+")]
+    b: u32,
+}
+
+/// Struct with escaped or bracketed text that looks like named groups
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = r"(?P<text>\(?P<x>\)|[(?<x>]+)")]
+struct GroupLookalike {
+    text: String,
+}
+
+/// Structs whose first alternative only matches part of some inputs
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = "(?P<text>a|ab)")]
+struct AlternativeStruct {
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+struct AlternativeSequence(#[from_regex(pattern = "a|ab")] String);
+
+/// Struct with bounded matches, for incremental search
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = r"(?P<word>[a-z]{1,4})", max_len = 4)]
+struct ShortWord {
+    word: String,
+}
+
+/// Bounded matches depending on the text around them
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = r"\b(?P<word>[a-z]{1,4})\b", max_len = 6)]
+struct BoundedWord {
+    word: String,
+}
+
+/// Struct displayed in the form it's parsed from
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(
+    pattern = r"(?P<host>[a-z]+(?:\.[a-z]+)*):(?P<port>\d+)",
+    display = "{host}:{port}"
+)]
+struct Address {
+    host: String,
+    port: String,
+}
+
+/// Struct with several formats to choose between
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = r"(?P<client>\S+) - (?P<status>\d{3})", dialect = "apache")]
+#[from_regex(pattern = r"(?P<status>\d{3}) from (?P<client>\S+)", dialect = "nginx")]
+#[from_regex(default_dialect = "nginx")]
+struct Request {
+    client: String,
+    status: String,
+}
+
+/// `Address`, with its pattern in a file
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern_file = "src/tests/address.re")]
+#[from_regex(pattern_file = "src/tests/address_reversed.re", dialect = "reversed")]
+struct FileAddress {
+    host: String,
+    port: String,
+}
+
+/// Enum with its patterns in files
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+enum FileEnum {
+    #[from_regex(pattern_file = "src/tests/address.re")]
+    Address { host: String, port: String },
+
+    #[from_regex(pattern = "-")]
+    Dash,
+}
+
+/// Enum displayed in the form it's parsed from
+#[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+#[from_regex(pattern = r"(?P<Verbose>-v)|@(?P<Connect>\S+)", dialect = "short")]
+enum Setting {
+    #[from_regex(pattern = r"--(?P<_0>[a-z]+)=(?P<_1>\w+)", display = "--{_0}={_1}")]
+    Option(String, String),
+    #[from_regex(pattern = "--verbose", display = "--verbose")]
+    Verbose,
+    #[from_regex(transparent)]
+    Connect(Address),
+}
+
 #[test]
 fn matches() {
     assert_eq!(
@@ -116,6 +418,27 @@ fn matches() {
         NestedEnum::from_regex("abcdef").expect("Didn't match NestedEnum"),
         NestedEnum::Nested(MY_STRUCT.clone())
     );
+
+    // Prefix Enum (a later alternative must still match the whole input)
+    assert_eq!(
+        PrefixEnum::from_regex("ab").expect("Didn't match PrefixEnum"),
+        PrefixEnum::Long
+    );
+    assert_eq!(PrefixEnum::from_regex("abc"), None);
+
+    // Priority Enum (transparent variants can be tried before patterned ones)
+    assert_eq!(
+        PriorityEnum::from_regex("if").expect("Didn't match PriorityEnum"),
+        PriorityEnum::Keyword
+    );
+    assert_eq!(
+        PriorityEnum::from_regex("abcdef").expect("Didn't match PriorityEnum"),
+        PriorityEnum::Nested(MY_STRUCT.clone())
+    );
+    assert_eq!(
+        PriorityEnum::from_regex("abc").expect("Didn't match PriorityEnum"),
+        PriorityEnum::Identifier
+    );
 }
 
 const SEARCH_TEXT: &str = "abcdef, abc, a c ac bc ba bc";
@@ -156,4 +479,949 @@ fn searches() {
             NESTED_CAPTURED_PARTIAL.clone()
         ]
     );
+
+    // The longest of the overlapping variants is kept, regardless of order
+    let locations = PrefixEnum::match_locations("ab abcdef a")
+        .into_iter()
+        .map(|(range, value)| (crate::to_range(&range).unwrap(), value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        vec![
+            (0..2, PrefixEnum::Long),
+            (3..9, PrefixEnum::Nested(MY_STRUCT.clone())),
+            (10..11, PrefixEnum::Short),
+        ]
+    );
+
+    // Searching resumes after each kept match, so a variant's match
+    // overlapping it doesn't hide the one following it
+    let locations = ChainEnum::match_locations("abbb")
+        .into_iter()
+        .map(|(range, value)| (crate::to_range(&range).unwrap(), value))
+        .collect::<Vec<_>>();
+    assert_eq!(locations, vec![(0..2, ChainEnum::A), (2..4, ChainEnum::B)]);
+
+    // Equally long overlapping matches are settled by priority
+    assert_eq!(
+        PriorityEnum::matches("if abcdef elsewhere"),
+        vec![
+            PriorityEnum::Keyword,
+            PriorityEnum::Nested(MY_STRUCT.clone()),
+            PriorityEnum::Identifier,
+        ]
+    );
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_searches() {
+    let text = vec![SEARCH_TEXT; 64].join("\n");
+    assert_eq!(
+        FlatEnum::par_match_locations(&text, Split::Lines),
+        FlatEnum::match_locations(&text)
+    );
+
+    let split = Regex::new(", ").unwrap();
+    assert_eq!(
+        NestedEnum::par_matches(&text, Split::Pattern(&split)),
+        NestedEnum::matches(&text)
+    );
+
+    assert_eq!(
+        MyStruct::par_parse_lines("abcdef\nabc\nabcdef"),
+        vec![Some(MY_STRUCT.clone()), None, Some(MY_STRUCT.clone())]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn streams() {
+    use futures_util::StreamExt;
+
+    let lines = FlatEnum::parse_lines("abc\nc\r\nsomething else\n".as_bytes())
+        .map(|line| line.expect("Failed to read line"))
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(
+        lines,
+        vec![
+            Some(FLAT_CAPTURED_FULL.clone()),
+            Some(FlatEnum::Shorter),
+            Some(FlatEnum::Fallback)
+        ]
+    );
+
+    let records = NestedEnum::search_records(SEARCH_TEXT.replace(", ", ";").as_bytes(), b';')
+        .map(|value| value.expect("Failed to read record"))
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(records, NestedEnum::matches(SEARCH_TEXT));
+}
+
+#[test]
+fn extensions() {
+    assert_eq!("abcdef".parse_regex::<MyStruct>(), Some(MY_STRUCT.clone()));
+    assert_eq!(
+        String::from("c").parse_regex::<FlatEnum>(),
+        Some(FlatEnum::Shorter)
+    );
+    assert_eq!(
+        SEARCH_TEXT.find_all::<NestedEnum>(),
+        NestedEnum::matches(SEARCH_TEXT)
+    );
+
+    let lines = "abcdef\nabc\nabcdef";
+    assert_eq!(
+        lines.lines().parse_regex::<MyStruct>().collect::<Vec<_>>(),
+        vec![Ok(MY_STRUCT.clone()), Err("abc"), Ok(MY_STRUCT.clone())]
+    );
+    assert_eq!(lines.lines().filter_parse::<MyStruct>().count(), 2);
+}
+
+#[test]
+fn search_options() {
+    // No policy is the same as the default search
+    assert_eq!(
+        FlatEnum::match_locations_with(SEARCH_TEXT, &SearchOptions::new()),
+        FlatEnum::match_locations(SEARCH_TEXT)
+    );
+    assert_eq!(
+        MyStruct::match_locations_with(SEARCH_TEXT, &SearchOptions::new()),
+        MyStruct::match_locations(SEARCH_TEXT)
+    );
+
+    // `FlatEnum` behaves like `SortedEnum` when keeping the first found
+    let first = SearchOptions::new().policy(OverlapPolicy::FirstFound);
+    assert_eq!(
+        locations(FlatEnum::match_locations_with(SEARCH_TEXT, &first)),
+        vec![
+            (2..3, FlatEnum::Shorter),
+            (10..11, FlatEnum::Shorter),
+            (15..16, FlatEnum::Shorter),
+            (18..19, FlatEnum::Shorter),
+            (21..22, FlatEnum::Shorter),
+            (27..28, FlatEnum::Shorter),
+        ]
+    );
+
+    // And `SortedEnum` keeps the longest (with both groups optional, "bc"
+    // matches as a whole rather than as "c")
+    let longest = SearchOptions::new().policy(OverlapPolicy::LeftmostLongest);
+    assert_eq!(
+        locations(SortedEnum::match_locations_with(SEARCH_TEXT, &longest)),
+        vec![
+            (0..3, SORTED_CAPTURED_FULL.clone()),
+            (8..11, SORTED_CAPTURED_FULL.clone()),
+            (15..16, SortedEnum::Shorter),
+            (
+                17..19,
+                SortedEnum::Capturing {
+                    a: Some(String::from("a")),
+                    b: None,
+                }
+            ),
+            (
+                20..22,
+                SortedEnum::Capturing {
+                    a: None,
+                    b: Some(String::from("b")),
+                }
+            ),
+            (
+                26..28,
+                SortedEnum::Capturing {
+                    a: None,
+                    b: Some(String::from("b")),
+                }
+            ),
+        ]
+    );
+    // Keywords win over identifiers by priority, even when shorter
+    let priority = SearchOptions::new().policy(OverlapPolicy::HighestPriority);
+    assert_eq!(
+        locations(PriorityEnum::match_locations_with(
+            "abcdef elsewhere",
+            &priority
+        )),
+        vec![
+            (0..6, PriorityEnum::Nested(MY_STRUCT.clone())),
+            (7..11, PriorityEnum::Keyword)
+        ]
+    );
+
+    // Custom ordering (shortest first)
+    let shortest = SearchOptions::new().policy(OverlapPolicy::Custom(Box::new(|a, b| {
+        a.range.len().cmp(&b.range.len())
+    })));
+    assert_eq!(
+        locations(NestedEnum::match_locations_with("abcdef", &shortest)),
+        vec![(0..3, NESTED_CAPTURED_FULL.clone())]
+    );
+}
+
+/// The ranges and values of a search's matches, in order
+fn locations<T>(map: SegmentMap<usize, T>) -> Vec<(Range<usize>, T)> {
+    map.into_iter()
+        .map(|(range, value)| (crate::to_range(&range).unwrap(), value))
+        .collect()
+}
+
+#[test]
+fn overlapping() {
+    assert_eq!(
+        PrefixEnum::match_all_overlapping("abcdef"),
+        vec![
+            (0..1, PrefixEnum::Short),
+            (0..2, PrefixEnum::Long),
+            (0..6, PrefixEnum::Nested(MY_STRUCT.clone())),
+        ]
+    );
+
+    // Includes matches nested within other matches of the same variant
+    assert_eq!(
+        PriorityEnum::match_all_overlapping("if"),
+        vec![
+            (0..2, PriorityEnum::Keyword),
+            (0..2, PriorityEnum::Identifier),
+            (1..2, PriorityEnum::Identifier),
+        ]
+    );
+
+    assert_eq!(
+        MyStruct::match_all_overlapping("abcdefabcdef"),
+        vec![(0..6, MY_STRUCT.clone()), (6..12, MY_STRUCT.clone())]
+    );
+}
+
+#[test]
+fn ambiguity() {
+    assert_eq!(
+        PriorityEnum::from_regex_all("abcdef"),
+        vec![
+            PriorityEnum::Nested(MY_STRUCT.clone()),
+            PriorityEnum::Identifier
+        ]
+    );
+    assert!(PriorityEnum::is_ambiguous("if"));
+    assert!(!PriorityEnum::is_ambiguous("abc"));
+
+    // Only the variant spanning the whole input matches, and default
+    // variants are never included
+    assert_eq!(PrefixEnum::from_regex_all("ab"), vec![PrefixEnum::Long]);
+    assert_eq!(FlatEnum::from_regex_all("something else"), vec![]);
+    assert_eq!(
+        SortedEnum::from_regex_all("c"),
+        vec![
+            SortedEnum::Shorter,
+            SortedEnum::Capturing { a: None, b: None }
+        ]
+    );
+}
+
+#[test]
+fn transparent_shapes() {
+    assert_eq!(
+        ShapesEnum::from_regex("abcdef").expect("Didn't match ShapesEnum"),
+        ShapesEnum::Named {
+            inner: MY_STRUCT.clone()
+        }
+    );
+    assert_eq!(
+        ShapesEnum::from_regex("ab").expect("Didn't match ShapesEnum"),
+        ShapesEnum::Tagged(String::from("prefix"), Box::new(PrefixEnum::Long), 0)
+    );
+    assert_eq!(
+        ShapesEnum::matches("ab c"),
+        vec![
+            ShapesEnum::Tagged(String::from("prefix"), Box::new(PrefixEnum::Long), 0),
+            ShapesEnum::Shared(std::sync::Arc::new(SortedEnum::Shorter)),
+        ]
+    );
+}
+
+#[test]
+fn transparent_structs() {
+    assert_eq!(
+        NestedNewtype::from_regex("abc").expect("Didn't match NestedNewtype"),
+        NestedNewtype(NESTED_CAPTURED_FULL.clone())
+    );
+    assert_eq!(
+        NestedNewtype::matches("abcdef ac"),
+        vec![
+            NestedNewtype(NestedEnum::Nested(MY_STRUCT.clone())),
+            NestedNewtype(NESTED_CAPTURED_PARTIAL.clone()),
+        ]
+    );
+
+    // Validation
+    assert_eq!(
+        KeywordNewtype::from_regex("if"),
+        Some(KeywordNewtype(PriorityEnum::Keyword))
+    );
+    assert_eq!(KeywordNewtype::from_regex("foo"), None);
+    assert_eq!(
+        KeywordNewtype::matches("if foo else"),
+        vec![
+            KeywordNewtype(PriorityEnum::Keyword),
+            KeywordNewtype(PriorityEnum::Keyword),
+        ]
+    );
+}
+
+#[test]
+fn default_input() {
+    assert_eq!(InputEnum::from_regex("c"), Some(InputEnum::Known));
+    assert_eq!(
+        InputEnum::from_regex("xyz"),
+        Some(InputEnum::Other(String::from("xyz")))
+    );
+    assert_eq!(
+        LabeledEnum::from_regex("xyz"),
+        Some(LabeledEnum::Other {
+            text: String::from("xyz"),
+            count: 0
+        })
+    );
+    assert_eq!(
+        ConstructedEnum::from_regex("xyz"),
+        Some(ConstructedEnum::Unknown(3))
+    );
+    assert_eq!(
+        ClosureEnum::from_regex("xyzé"),
+        Some(ClosureEnum::Unknown(4))
+    );
+    assert_eq!(
+        QualifiedInputEnum::from_regex("xyz"),
+        Some(QualifiedInputEnum::Other(String::from("xyz")))
+    );
+}
+
+#[test]
+fn tokenize() {
+    assert_eq!(
+        NestedEnum::tokenize("xx abcdef ac").collect::<Vec<_>>(),
+        vec![
+            Token::Gap(0..3, "xx "),
+            Token::Match(3..9, NestedEnum::Nested(MY_STRUCT.clone())),
+            Token::Gap(9..10, " "),
+            Token::Match(10..12, NESTED_CAPTURED_PARTIAL.clone()),
+        ]
+    );
+    assert_eq!(
+        PrefixEnum::tokenize("aab!").collect::<Vec<_>>(),
+        vec![
+            Token::Match(0..1, PrefixEnum::Short),
+            Token::Match(1..3, PrefixEnum::Long),
+            Token::Gap(3..4, "!"),
+        ]
+    );
+    assert_eq!(MyStruct::tokenize("").count(), 0);
+}
+
+#[test]
+fn lexer() {
+    let tokens = LexerEnum::lex(r#"if iffy "a \"b" c"#)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to lex");
+    assert_eq!(
+        tokens,
+        vec![
+            (0..2, LexerEnum::If),
+            (
+                3..7,
+                LexerEnum::Identifier {
+                    name: String::from("iffy")
+                }
+            ),
+            (8..9, LexerEnum::Quote),
+            (
+                9..11,
+                LexerEnum::Text {
+                    text: String::from("a ")
+                }
+            ),
+            (
+                11..13,
+                LexerEnum::Escape {
+                    escaped: String::from("\"")
+                }
+            ),
+            (
+                13..14,
+                LexerEnum::Text {
+                    text: String::from("b")
+                }
+            ),
+            (14..15, LexerEnum::EndQuote),
+            (
+                16..17,
+                LexerEnum::Identifier {
+                    name: String::from("c")
+                }
+            ),
+        ]
+    );
+
+    // Lexing stops at the first byte no token matches
+    let mut lex = LexerEnum::lex("a 1 b");
+    assert!(matches!(lex.next(), Some(Ok(_))));
+    assert_eq!(
+        lex.next(),
+        Some(Err(LexError {
+            position: 2,
+            state: None
+        }))
+    );
+    assert_eq!(lex.next(), None);
+
+    // Tokens are matched with the preceding input as context
+    let tokens = UnitLexer::lex("10km far")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to lex");
+    assert_eq!(
+        tokens,
+        vec![
+            (0..2, UnitLexer::Number),
+            (2..4, UnitLexer::Unit),
+            (5..8, UnitLexer::Word),
+        ]
+    );
+}
+
+#[test]
+fn scanner() {
+    let scanner = Scanner::new().with::<MyStruct>().with::<PrefixEnum>();
+    let hits = scanner.scan("ab abcdef");
+    assert_eq!(
+        hits.iter()
+            .map(|(range, _)| range.clone())
+            .collect::<Vec<_>>(),
+        vec![0..2, 3..9]
+    );
+    assert_eq!(
+        hits[0].1.downcast_ref::<PrefixEnum>(),
+        Some(&PrefixEnum::Long)
+    );
+    // Ties go to the type registered first
+    assert_eq!(hits[1].1.downcast_ref::<MyStruct>(), Some(&*MY_STRUCT));
+
+    #[derive(Debug, PartialEq)]
+    enum Scanned {
+        Flat(FlatEnum),
+        Keyword(KeywordNewtype),
+    }
+    let scanner = Scanner::default()
+        .with_map(Scanned::Flat)
+        .with_map(Scanned::Keyword);
+    assert_eq!(
+        scanner.scan("if abc"),
+        vec![
+            (
+                0..2,
+                Scanned::Keyword(KeywordNewtype(PriorityEnum::Keyword))
+            ),
+            (3..6, Scanned::Flat(FLAT_CAPTURED_FULL.clone())),
+        ]
+    );
+    assert_eq!(scanner.scan("nothing"), vec![]);
+
+    // A type's match overlapping one kept doesn't hide the one after it
+    #[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+    #[from_regex(pattern = "(?P<text>bb)")]
+    struct Pair {
+        text: String,
+    }
+    let hits = Scanner::new()
+        .with::<PrefixEnum>()
+        .with::<Pair>()
+        .scan("abbb");
+    assert_eq!(
+        hits.iter()
+            .map(|(range, _)| range.clone())
+            .collect::<Vec<_>>(),
+        vec![0..2, 2..4]
+    );
+    assert_eq!(
+        hits[1].1.downcast_ref::<Pair>(),
+        Some(&Pair {
+            text: String::from("bb")
+        })
+    );
+}
+
+#[test]
+fn linear_search() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A hand-written type, counting how often it searches the entire string
+    static SEARCHES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Counted;
+
+    impl FromRegex for Counted {
+        fn from_regex(s: &str) -> Option<Self> {
+            if s == "x" {
+                Some(Counted)
+            } else {
+                None
+            }
+        }
+
+        fn match_locations(s: &str) -> SegmentMap<usize, Self> {
+            SEARCHES.fetch_add(1, Ordering::SeqCst);
+            let mut locations = SegmentMap::new();
+            for (i, _) in s.match_indices('x') {
+                locations.insert(i..i + 1, Counted);
+            }
+            locations
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+    enum CountedEnum {
+        #[from_regex(transparent)]
+        Counted(Counted),
+
+        #[from_regex(pattern = "y")]
+        Other,
+    }
+
+    // Resuming after each match doesn't search the entire string again
+    let text = "xy".repeat(1000);
+    assert_eq!(CountedEnum::matches(&text).len(), 2000);
+    assert_eq!(SEARCHES.load(Ordering::SeqCst), 1);
+
+    let scanner = Scanner::new().with::<Counted>().with::<FlatEnum>();
+    assert_eq!(scanner.scan(&text).len(), 1000);
+    assert_eq!(SEARCHES.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn sequences() {
+    assert_eq!(
+        Command::from_regex("repeat 3  abc"),
+        Some(Command {
+            name: String::from("repeat"),
+            count: 3,
+            target: SORTED_CAPTURED_FULL.clone(),
+        })
+    );
+    assert_eq!(Command::from_regex("repeat x abc"), None);
+
+    // Parsing is anchored to the entire input, rather than checking the
+    // length of the first match
+    assert_eq!(
+        AlternativeStruct::from_regex("ab"),
+        Some(AlternativeStruct {
+            text: String::from("ab")
+        })
+    );
+    assert_eq!(
+        AlternativeSequence::from_regex("ab"),
+        Some(AlternativeSequence(String::from("ab")))
+    );
+    assert_eq!(AlternativeSequence::from_regex("abc"), None);
+
+    // Alternatives in the separator stay between the fields
+    assert_eq!(
+        SeparatedPair::from_regex("1,2"),
+        Some(SeparatedPair { a: 1, b: 2 })
+    );
+    assert_eq!(
+        SeparatedPair::from_regex("1;2"),
+        Some(SeparatedPair { a: 1, b: 2 })
+    );
+    assert_eq!(SeparatedPair::from_regex("1,"), None);
+    assert_eq!(
+        Command::matches("run 1 c; skip 99999999999 c"),
+        vec![Command {
+            name: String::from("run"),
+            count: 1,
+            target: SortedEnum::Shorter,
+        }]
+    );
+}
+
+#[test]
+fn non_capturing() {
+    // Only real groups are made non-capturing
+    let pattern = non_capturing_pattern::<GroupLookalike>().unwrap();
+    assert_eq!(pattern, r"(?:(?:\(?P<x>\)|[(?<x>]+))");
+    let regex = Regex::new(&pattern).unwrap();
+    assert_eq!(regex.captures_len(), 1);
+    assert!(regex.is_match("P<x>)"));
+    assert!(regex.is_match("(<x"));
+}
+
+#[test]
+fn nested_search() {
+    assert_eq!(
+        NestedEnum::match_tree("abcdef ac"),
+        vec![
+            MatchTree {
+                range: 0..6,
+                value: NestedEnum::Nested(MY_STRUCT.clone()),
+                children: vec![MatchTree {
+                    range: 0..3,
+                    value: NESTED_CAPTURED_FULL.clone(),
+                    children: vec![],
+                }],
+            },
+            MatchTree {
+                range: 7..9,
+                value: NESTED_CAPTURED_PARTIAL.clone(),
+                children: vec![],
+            },
+        ]
+    );
+
+    // Only inside an `abcdef`
+    assert_eq!(
+        MyStruct::search_within::<FlatEnum>("c abcdef c"),
+        vec![(2..5, FLAT_CAPTURED_FULL.clone())]
+    );
+
+    // Inner patterns see the text around the outer matches
+    #[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+    #[from_regex(pattern = "(?P<text>bc)")]
+    struct Outer {
+        text: String,
+    }
+    #[derive(Debug, Clone, PartialEq, Eq, FromRegex)]
+    #[from_regex(pattern = r"\b(?P<text>bc)")]
+    struct WordStart {
+        text: String,
+    }
+    assert_eq!(
+        Outer::search_within::<WordStart>("abc bc"),
+        vec![(
+            4..6,
+            WordStart {
+                text: String::from("bc")
+            }
+        )]
+    );
+}
+
+#[test]
+fn incremental() {
+    let text = "abc defghij kl mnopq r";
+    let edits = [
+        (4..4, "x"),
+        (0..3, ""),
+        (9..13, "zz yy"),
+        (20..22, "stuvw"),
+        (3..4, "-é-"),
+        (0..22, "a"),
+    ];
+    for (range, replacement) in edits {
+        let mut edited = String::from(text);
+        edited.replace_range(range.clone(), replacement);
+
+        let updated = ShortWord::update_locations(
+            ShortWord::match_locations(text),
+            &Edit::new(range, replacement),
+            &edited,
+        );
+        assert_eq!(
+            updated.into_iter().collect::<Vec<_>>(),
+            ShortWord::match_locations(&edited)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            "{:?}",
+            edited
+        );
+    }
+
+    // Rescanning sees the text around the edit, so word boundaries aren't
+    // found at the edges of the rescanned window
+    let text = "aaaaaaaa bb";
+    let edit = Edit::new(10..11, "c");
+    let updated =
+        BoundedWord::update_locations(BoundedWord::match_locations(text), &edit, "aaaaaaaa bc");
+    assert_eq!(
+        updated.into_iter().collect::<Vec<_>>(),
+        BoundedWord::match_locations("aaaaaaaa bc")
+            .into_iter()
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn highlight() {
+    let text = "c <abc> abcdef";
+    let html = Highlighter::html()
+        .class_prefix("re-")
+        .render(text, &NestedEnum::match_locations(text));
+    assert_eq!(
+        html,
+        "c &lt;<span class=\"re-Capturing\">abc</span>&gt; <span class=\"re-Nested\">abcdef</span>"
+    );
+    assert_eq!(
+        Highlighter::html().render_tokens(text, NestedEnum::tokenize(text)),
+        Highlighter::html().render(text, &NestedEnum::match_locations(text))
+    );
+
+    let ansi = Highlighter::ansi()
+        .color("Capturing", "1;34")
+        .render(text, &NestedEnum::match_locations(text));
+    assert!(ansi.starts_with("c <\x1b[1;34mabc\x1b[0m> \x1b["));
+    assert_eq!(MY_STRUCT.variant_name(), "MyStruct");
+}
+
+#[test]
+fn replace() {
+    assert_eq!(
+        NestedEnum::replace_all("x abcdef ac", |value| match value {
+            NestedEnum::Capturing { a, b } => {
+                Cow::Owned(format!("{}{}", a, b.as_deref().unwrap_or("-")))
+            }
+            NestedEnum::Nested(inner) => Cow::Owned(inner.named.clone()),
+        }),
+        "x def a-"
+    );
+
+    // Replacements can borrow from outside the closure
+    let names = [String::from("nested"), String::from("capturing")];
+    assert_eq!(
+        NestedEnum::replace_all("x abcdef ac", |value| match value {
+            NestedEnum::Nested(_) => Cow::Borrowed(names[0].as_str()),
+            NestedEnum::Capturing { .. } => Cow::Borrowed(names[1].as_str()),
+        }),
+        "x nested capturing"
+    );
+    assert_eq!(NestedEnum::redact("x abc, ac", "***"), "x ***, ***");
+    assert!(matches!(
+        NestedEnum::redact("nothing", "***"),
+        Cow::Borrowed("nothing")
+    ));
+}
+
+#[test]
+fn display() {
+    let address = Address {
+        host: "example.com".into(),
+        port: "8080".into(),
+    };
+    assert_eq!(address.to_string(), "example.com:8080");
+    assert_round_trip(&address);
+
+    let option = Setting::Option("color".into(), "auto".into());
+    assert_eq!(option.to_string(), "--color=auto");
+    assert_round_trip(&option);
+    assert_round_trip(&Setting::Verbose);
+    assert_eq!(
+        Setting::Connect(address.clone()).to_string(),
+        "example.com:8080"
+    );
+    assert_round_trip(&Setting::Connect(address));
+}
+
+#[test]
+#[should_panic(expected = "which parses as None")]
+fn display_mismatch() {
+    assert_round_trip(&Address {
+        host: "Example".into(),
+        port: "80".into(),
+    });
+}
+
+#[test]
+fn from_captures() {
+    let regex =
+        Regex::new(r"(?P<server_host>[a-z.]+):(?P<server_port>\d+) as (?P<named>\w+)").unwrap();
+    let captures = regex.captures("example.com:8080 as admin").unwrap();
+    assert_eq!(
+        Address::from_captures_prefixed(&captures, "server_"),
+        Some(Address {
+            host: "example.com".into(),
+            port: "8080".into(),
+        })
+    );
+    assert_eq!(MyStruct::from_captures(&captures).unwrap().named, "admin");
+    assert_eq!(Address::from_captures(&captures), None);
+
+    // Variants are chosen by the group named after them
+    let regex =
+        Regex::new(r"(?P<Capturing>(?P<Capturing_a>\w)(?P<Capturing_b>!)?)|(?P<Shorter>\.)")
+            .unwrap();
+    let captures = regex.captures("x").unwrap();
+    assert_eq!(
+        FlatEnum::from_captures(&captures),
+        Some(FlatEnum::Capturing {
+            a: "x".into(),
+            b: None
+        })
+    );
+    let captures = regex.captures(".").unwrap();
+    assert_eq!(FlatEnum::from_captures(&captures), Some(FlatEnum::Shorter));
+
+    let regex = Regex::new(r"<(?P<in_Nested>.*)>").unwrap();
+    let captures = regex.captures("<abcdef>").unwrap();
+    assert_eq!(
+        NestedEnum::from_captures_prefixed(&captures, "in_"),
+        Some(NestedEnum::Nested(MY_STRUCT.clone()))
+    );
+    let captures = regex.captures("<abc>").unwrap();
+    assert_eq!(NestedEnum::from_captures_prefixed(&captures, "in_"), None);
+
+    // Groups are named the same way as in the derived pattern
+    let regex =
+        Regex::new(r"(?P<Hidden>(?P<Hidden__kind>\w+)!)|(?P<Pair>(?P<Pair_0>\w)(?P<Pair_1>\d))")
+            .unwrap();
+    let hidden = UnderscoreEnum::Hidden {
+        _kind: "abc".into(),
+    };
+    let pair = UnderscoreEnum::Pair("a".into(), "1".into());
+    assert_eq!(
+        UnderscoreEnum::from_captures(&regex.captures("abc!").unwrap()),
+        Some(hidden.clone())
+    );
+    assert_eq!(
+        UnderscoreEnum::from_captures(&regex.captures("a1").unwrap()),
+        Some(pair.clone())
+    );
+    assert_eq!(UnderscoreEnum::from_regex("abc!"), Some(hidden));
+    assert_eq!(UnderscoreEnum::from_regex("a1"), Some(pair));
+
+    // Groups are renamed whichever syntax names them
+    assert_eq!(
+        AngleGroupEnum::from_regex("123"),
+        Some(AngleGroupEnum::Number("123".into()))
+    );
+    assert_eq!(
+        AngleGroupEnum::matches("12 ab!"),
+        vec![
+            AngleGroupEnum::Number("12".into()),
+            AngleGroupEnum::Named { name: "ab".into() }
+        ]
+    );
+}
+
+#[test]
+fn parser_with_pattern() {
+    let parser = Address::parser_with_pattern(r"host=(?P<host>\S+) port=(?P<port>\d+)").unwrap();
+    let address = Address {
+        host: "example.com".into(),
+        port: "80".into(),
+    };
+    assert_eq!(
+        parser.parse("host=example.com port=80"),
+        Some(address.clone())
+    );
+    assert_eq!(parser.parse("host=example.com port=80!"), None);
+    assert_eq!(
+        parser
+            .match_locations("a host=example.com port=80, host=x port=1")
+            .into_iter()
+            .next(),
+        Some((segmap::Segment::from(2..26), address))
+    );
+
+    assert!(matches!(
+        Address::parser_with_pattern(r"(?P<host>\S+)"),
+        Err(PatternError::MissingGroup("port"))
+    ));
+    assert!(matches!(
+        Address::parser_with_pattern(r"(?P<host>\S+)(:(?P<port>\d+))?"),
+        Err(PatternError::OptionalGroup("port"))
+    ));
+    assert!(matches!(
+        Address::parser_with_pattern(r"(?P<host>\S+"),
+        Err(PatternError::Regex(_))
+    ));
+
+    // Optional fields can be left out, and variants need only their own
+    // groups
+    let parser =
+        FlatEnum::parser_with_pattern(r"(?P<Capturing>(?P<Capturing_a>\w)=)|(?P<Shorter>\.)")
+            .unwrap();
+    assert_eq!(
+        parser.parse("x="),
+        Some(FlatEnum::Capturing {
+            a: "x".into(),
+            b: None
+        })
+    );
+    assert_eq!(parser.parse("."), Some(FlatEnum::Shorter));
+    assert!(FlatEnum::parser_with_pattern(r"(?P<Shorter>\.)").is_ok());
+    assert!(matches!(
+        FlatEnum::parser_with_pattern(r"(?P<Capturing>(?P<Capturing_a>\w)?=)"),
+        Err(PatternError::OptionalGroup("Capturing_a"))
+    ));
+    assert!(matches!(
+        FlatEnum::parser_with_pattern(r"\w+"),
+        Err(PatternError::NoVariants)
+    ));
+}
+
+#[test]
+fn dialects() {
+    let request = Request {
+        client: "10.0.0.1".into(),
+        status: "404".into(),
+    };
+    assert_eq!(Request::dialects(), &["apache", "nginx"]);
+    assert_eq!(
+        Request::from_regex_dialect("10.0.0.1 - 404", "apache"),
+        Some(request.clone())
+    );
+    assert_eq!(Request::from_regex_dialect("10.0.0.1 - 404", "nginx"), None);
+    assert_eq!(Request::from_regex_dialect("10.0.0.1 - 404", "iis"), None);
+
+    // The default dialect is the struct's own pattern
+    assert_eq!(
+        Request::from_regex("404 from 10.0.0.1"),
+        Some(request.clone())
+    );
+    assert_eq!(
+        Request::dialect_parser("apache")
+            .unwrap()
+            .match_locations("a 10.0.0.1 - 404\n")
+            .into_iter()
+            .next(),
+        Some((segmap::Segment::from(2..16), request))
+    );
+
+    assert_eq!(
+        Setting::from_regex_dialect("-v", "short"),
+        Some(Setting::Verbose)
+    );
+    assert!(matches!(
+        Setting::from_regex_dialect("@example.com:22", "short"),
+        Some(Setting::Connect(Address { port, .. })) if port == "22"
+    ));
+    assert_eq!(Setting::from_regex("-v"), None);
+}
+
+#[test]
+fn pattern_file() {
+    assert_eq!(
+        FileAddress::from_regex("example.com:80"),
+        Some(FileAddress {
+            host: "example.com".into(),
+            port: "80".into(),
+        })
+    );
+    assert_eq!(FileAddress::from_regex("example.com: 80"), None);
+    assert_eq!(
+        FileEnum::match_locations("-a.b:1-")
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>(),
+        vec![
+            FileEnum::Dash,
+            FileEnum::Address {
+                host: "a.b".into(),
+                port: "1".into()
+            },
+            FileEnum::Dash,
+        ]
+    );
+    assert_eq!(
+        FileAddress::from_regex_dialect("80 on example.com", "reversed"),
+        FileAddress::from_regex("example.com:80")
+    );
 }
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use crate::to_range;
+
+/// A piece of the input to [`FromRegex::tokenize`](crate::FromRegex::tokenize):
+/// either a match, or the text between matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'s, T> {
+    /// A match, and the byte range of the input it spans
+    Match(Range<usize>, T),
+
+    /// Unmatched text, and the byte range of the input it spans
+    Gap(Range<usize>, &'s str),
+}
+
+impl<'s, T> Token<'s, T> {
+    /// The byte range of the input this token spans
+    pub fn range(&self) -> &Range<usize> {
+        match self {
+            Token::Match(range, _) | Token::Gap(range, _) => range,
+        }
+    }
+
+    /// Whether this token is a match (rather than a gap)
+    pub fn is_match(&self) -> bool {
+        matches!(self, Token::Match(..))
+    }
+}
+
+/// Iterator returned by [`FromRegex::tokenize`](crate::FromRegex::tokenize)
+#[derive(Debug)]
+pub struct Tokens<'s, T> {
+    text: &'s str,
+    pos: usize,
+    matches: std::vec::IntoIter<(Range<usize>, T)>,
+    next_match: Option<(Range<usize>, T)>,
+}
+
+impl<'s, T> Tokens<'s, T> {
+    /// Interleave `matches` (which must be ordered and not overlap) with the
+    /// gaps between them
+    pub(crate) fn new(text: &'s str, matches: crate::SegmentMap<usize, T>) -> Self {
+        let matches = matches
+            .into_iter()
+            .filter_map(|(range, value)| Some((to_range(&range)?, value)))
+            .collect::<Vec<_>>()
+            .into_iter();
+        Self {
+            text,
+            pos: 0,
+            matches,
+            next_match: None,
+        }
+    }
+}
+
+impl<'s, T> Iterator for Tokens<'s, T> {
+    type Item = Token<'s, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_match.is_none() {
+            self.next_match = self.matches.next();
+        }
+
+        // Text before the next match (or the end of the input) is a gap
+        let gap_end = match &self.next_match {
+            Some((range, _)) => range.start,
+            None => self.text.len(),
+        };
+        if self.pos < gap_end {
+            let range = self.pos..gap_end;
+            self.pos = gap_end;
+            return Some(Token::Gap(range.clone(), &self.text[range]));
+        }
+
+        let (range, value) = self.next_match.take()?;
+        self.pos = range.end;
+        Some(Token::Match(range, value))
+    }
+}
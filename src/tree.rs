@@ -0,0 +1,102 @@
+use std::cmp::Reverse;
+use std::ops::Range;
+
+/// A match, along with the matches found within it (see
+/// [`FromRegex::match_tree`](crate::FromRegex::match_tree))
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTree<T> {
+    /// Byte range of the match in the searched text
+    pub range: Range<usize>,
+
+    /// The matched value
+    pub value: T,
+
+    /// Matches within this one (and not within each other), in order
+    pub children: Vec<MatchTree<T>>,
+}
+
+impl<T> MatchTree<T> {
+    /// Iterate over this match and every match within it, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &MatchTree<T>> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+}
+
+/// Build a tree under each of `roots` from `hits` within their spans (except
+/// the hit for the root itself). Hits that cross the end of an earlier,
+/// longer hit are dropped.
+pub(crate) fn grow<T: Eq>(
+    roots: Vec<(Range<usize>, T)>,
+    mut hits: Vec<(Range<usize>, T)>,
+) -> Vec<MatchTree<T>> {
+    // Outer matches first, so each hit comes after anything containing it
+    hits.sort_by_key(|(range, _)| (range.start, Reverse(range.len())));
+
+    let mut hits = hits.into_iter().map(Some).collect::<Vec<_>>();
+    roots
+        .into_iter()
+        .map(|(range, value)| {
+            let mut within = Vec::new();
+            let mut found_root = false;
+            for slot in hits.iter_mut() {
+                let contained = matches!(slot, Some((hit, _)) if hit.start >= range.start && hit.end <= range.end);
+                if !contained {
+                    continue;
+                }
+                let (hit, hit_value) = slot.take().unwrap();
+                if !found_root && hit == range && hit_value == value {
+                    found_root = true;
+                } else {
+                    within.push((hit, hit_value));
+                }
+            }
+            MatchTree {
+                range,
+                value,
+                children: nest(within),
+            }
+        })
+        .collect()
+}
+
+/// Nest ordered hits (outer matches first) under the hits containing them
+fn nest<T>(hits: Vec<(Range<usize>, T)>) -> Vec<MatchTree<T>> {
+    let mut nodes: Vec<MatchTree<T>> = Vec::new();
+    let mut open: Vec<MatchTree<T>> = Vec::new();
+
+    for (range, value) in hits {
+        // Close everything ending before this hit
+        while open
+            .last()
+            .is_some_and(|node| node.range.end <= range.start)
+        {
+            let node = open.pop().unwrap();
+            match open.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => nodes.push(node),
+            }
+        }
+
+        if open.last().is_some_and(|node| range.end > node.range.end) {
+            continue;
+        }
+        open.push(MatchTree {
+            range,
+            value,
+            children: Vec::new(),
+        });
+    }
+
+    while let Some(node) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => nodes.push(node),
+        }
+    }
+    nodes
+}